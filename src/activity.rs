@@ -0,0 +1,137 @@
+//! Who changed which files and when, gathered in one walk of the history. Hotspot scores, the
+//! rules that look at the history and the reports that combine the views of the `hottest`,
+//! `busfactor` and `contributors` commands all read from it.
+
+use crate::error::HotspotError;
+use crate::history::CommitRecord;
+use crate::shared_types::{ContributorKey, RawPath};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone)]
+pub struct FileActivity {
+    /// Number of commits that changed the file.
+    pub touches: u32,
+    pub last_changed_at: i64,
+    pub last_changed_by: ContributorKey,
+    /// Number of commits each person made to the file.
+    pub contributors: HashMap<ContributorKey, u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContributorActivity {
+    pub commits: u32,
+    pub files: HashSet<RawPath>,
+    pub first_commit_at: i64,
+    pub last_commit_at: i64,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    pub files: HashMap<RawPath, FileActivity>,
+    pub contributors: HashMap<ContributorKey, ContributorActivity>,
+    pub commits: usize,
+    pub first_commit_at: Option<i64>,
+    pub last_commit_at: Option<i64>,
+}
+
+impl Activity {
+    pub fn collect<I>(history: I) -> Result<Self, HotspotError>
+    where
+        I: IntoIterator<Item = Result<CommitRecord, HotspotError>>,
+    {
+        let mut activity = Activity::default();
+        for record in history {
+            activity.add(record?);
+        }
+        Ok(activity)
+    }
+
+    /// Adds a commit, in any order.
+    pub fn add(&mut self, record: CommitRecord) {
+        let CommitRecord {
+            author,
+            time,
+            deltas,
+            ..
+        } = record;
+        self.commits += 1;
+        self.first_commit_at = Some(self.first_commit_at.map_or(time, |t| t.min(time)));
+        self.last_commit_at = Some(self.last_commit_at.map_or(time, |t| t.max(time)));
+
+        let contributor =
+            self.contributors
+                .entry(author.clone())
+                .or_insert_with(|| ContributorActivity {
+                    commits: 0,
+                    files: HashSet::new(),
+                    first_commit_at: time,
+                    last_commit_at: time,
+                    additions: 0,
+                    deletions: 0,
+                });
+        contributor.commits += 1;
+        contributor.first_commit_at = contributor.first_commit_at.min(time);
+        contributor.last_commit_at = contributor.last_commit_at.max(time);
+        for delta in deltas {
+            contributor.additions += delta.additions;
+            contributor.deletions += delta.deletions;
+            contributor.files.insert(delta.path.clone());
+            let file = self
+                .files
+                .entry(delta.path)
+                .or_insert_with(|| FileActivity {
+                    touches: 0,
+                    last_changed_at: time,
+                    last_changed_by: author.clone(),
+                    contributors: HashMap::new(),
+                });
+            file.touches += 1;
+            if time > file.last_changed_at {
+                file.last_changed_at = time;
+                file.last_changed_by = author.clone();
+            }
+            *file.contributors.entry(author.clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::shared_types::{ContributorKey, RawPath};
+    use crate::test_support::record;
+
+    #[test]
+    fn files_and_contributors_are_tallied() {
+        let activity = Activity::collect(
+            vec![
+                record("bob", 3_000, &["a.rs"]),
+                record("alice", 2_000, &["a.rs", "b.rs"]),
+                record("alice", 1_000, &["a.rs"]),
+            ]
+            .into_iter()
+            .map(Ok),
+        )
+        .unwrap();
+        assert_eq!(activity.commits, 3);
+        assert_eq!(activity.first_commit_at, Some(1_000));
+        assert_eq!(activity.last_commit_at, Some(3_000));
+
+        let a = &activity.files[&RawPath::from("a.rs")];
+        assert_eq!(a.touches, 3);
+        assert_eq!(a.last_changed_by.name(), "bob");
+        assert_eq!(a.contributors.len(), 2);
+
+        let alice = ContributorKey::new("alice@example.com".to_string(), "alice".to_string());
+        let alice = &activity.contributors[&alice];
+        assert_eq!(alice.commits, 2);
+        assert_eq!(alice.files.len(), 2);
+        assert_eq!(
+            (alice.first_commit_at, alice.last_commit_at),
+            (1_000, 2_000)
+        );
+        assert_eq!(alice.additions, 6);
+    }
+}
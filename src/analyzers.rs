@@ -0,0 +1,438 @@
+//! Language analyzers turn the contents of a source file into metrics.
+//!
+//! The built-in analyzers wrap the rust-code-analysis parsers and the line [heuristic](crate::heuristic).
+//! Library users can support their own languages by implementing [`LanguageAnalyzer`] and adding it
+//! to an [`AnalyzerRegistry`]:
+//!
+//! ```
+//! use hotspot::analyzers::{AnalyzerRegistry, FileMetrics, LanguageAnalyzer};
+//! use std::path::Path;
+//!
+//! struct RulesDsl;
+//!
+//! impl LanguageAnalyzer for RulesDsl {
+//!     fn name(&self) -> &str {
+//!         "rules-dsl"
+//!     }
+//!     fn supports_extension(&self, ext: &str) -> bool {
+//!         ext == "rules"
+//!     }
+//!     fn analyze(&self, _path: &Path, contents: &[u8]) -> Option<FileMetrics> {
+//!         let loc = contents.split(|b| *b == b'\n').filter(|l| !l.is_empty()).count() as i64;
+//!         Some(FileMetrics { loc, cognitive: 0, cyclomatic: 1, functions: vec![] })
+//!     }
+//! }
+//!
+//! let mut registry = AnalyzerRegistry::default();
+//! registry.register(Box::new(RulesDsl));
+//! let metrics = registry.analyze(Path::new("pricing.rules"), b"when a\nthen b\n");
+//! assert_eq!(metrics.loc, Some(2));
+//! assert_eq!(metrics.analyzer.as_deref(), Some("rules-dsl"));
+//! ```
+
+use crate::cache::BlobCache;
+use crate::heuristic::{self, Dialect};
+use crate::shared_types::{RawPath, SpecificMetrics};
+use git2::{ObjectType, Oid};
+use rust_code_analysis::{
+    CppParser, FuncSpace, JavaParser, JavascriptParser, ParserTrait, PythonParser, RustParser,
+    SpaceKind, TsxParser, TypescriptParser, LANG,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Metrics for a whole file as produced by a [`LanguageAnalyzer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMetrics {
+    pub loc: i64,
+    pub cognitive: i64,
+    pub cyclomatic: i64,
+    pub functions: Vec<FunctionMetrics>,
+}
+
+/// Metrics for a single function within a file. Lines are 1-based and inclusive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub loc: i64,
+    pub cognitive: i64,
+    pub cyclomatic: i64,
+}
+
+/// Computes metrics for the languages it supports.
+pub trait LanguageAnalyzer: Send + Sync {
+    /// Name reported alongside the metrics so users know where the numbers came from.
+    fn name(&self) -> &str;
+    /// Whether files with the extension `ext` (without the leading `.`) are handled by this analyzer.
+    fn supports_extension(&self, ext: &str) -> bool;
+    /// Computes the metrics for `contents`, returning `None` when they cannot be parsed.
+    fn analyze(&self, path: &Path, contents: &[u8]) -> Option<FileMetrics>;
+}
+
+/// An ordered collection of analyzers. When more than one supports a file the most recently registered wins,
+/// so registering an analyzer for a built-in extension replaces the built-in one.
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Box<dyn LanguageAnalyzer>>,
+}
+
+impl AnalyzerRegistry {
+    /// A registry with no analyzers at all.
+    pub fn empty() -> Self {
+        AnalyzerRegistry { analyzers: vec![] }
+    }
+
+    /// A registry containing the analyzers for every language the tool supports out of the box.
+    pub fn with_builtins() -> Self {
+        let mut registry = AnalyzerRegistry::empty();
+        for lang in [
+            LANG::Rust,
+            LANG::Mozjs,
+            LANG::Typescript,
+            LANG::Tsx,
+            LANG::Python,
+            LANG::Cpp,
+        ] {
+            registry.register(Box::new(RcaAnalyzer { lang }));
+        }
+        registry.register(Box::new(JavaAnalyzer));
+        for dialect in [Dialect::CSharp, Dialect::FSharp, Dialect::Go] {
+            registry.register(Box::new(HeuristicAnalyzer { dialect }));
+        }
+        registry
+    }
+
+    pub fn register(&mut self, analyzer: Box<dyn LanguageAnalyzer>) {
+        self.analyzers.push(analyzer);
+    }
+
+    pub fn analyzer_for(&self, path: &Path) -> Option<&dyn LanguageAnalyzer> {
+        let ext = path.extension()?.to_str()?;
+        self.analyzers
+            .iter()
+            .rev()
+            .find(|a| a.supports_extension(ext))
+            .map(|a| a.as_ref())
+    }
+
+    /// Analyzes a single file. Files without an analyzer, or that fail to parse, have no metrics.
+    pub fn analyze(&self, path: &Path, contents: &[u8]) -> SpecificMetrics {
+        let p = RawPath::from_path(path);
+        match self.measure(path, contents) {
+            Some((analyzer, m)) => SpecificMetrics::analyzed(p, analyzer, m),
+            None => SpecificMetrics::unanalyzed(p),
+        }
+    }
+
+    /// Like [`AnalyzerRegistry::analyze`] but reuses the metrics of identical contents found in `cache`
+    /// and adds the ones it is missing.
+    pub fn analyze_cached(
+        &self,
+        path: &Path,
+        contents: &[u8],
+        cache: Option<&BlobCache>,
+    ) -> SpecificMetrics {
+        match Oid::hash_object(ObjectType::Blob, contents) {
+            Ok(oid) => self
+                .analyze_blob(path, oid, cache, || Ok::<_, Infallible>(contents))
+                .unwrap_or_else(|e| match e {}),
+            Err(_) => self.analyze(path, contents),
+        }
+    }
+
+    /// Like [`AnalyzerRegistry::analyze_cached`] for contents stored as the blob `oid`, which are only
+    /// read when `cache` does not have their metrics yet.
+    pub fn analyze_blob<C, E>(
+        &self,
+        path: &Path,
+        oid: Oid,
+        cache: Option<&BlobCache>,
+        read: impl FnOnce() -> Result<C, E>,
+    ) -> Result<SpecificMetrics, E>
+    where
+        C: AsRef<[u8]>,
+    {
+        let p = RawPath::from_path(path);
+        let analyzer = match self.analyzer_for(path) {
+            Some(analyzer) => analyzer,
+            None => return Ok(SpecificMetrics::unanalyzed(p)),
+        };
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let key = BlobCache::key(oid, ext, analyzer.name());
+        if let Some((name, metrics)) = cache.and_then(|c| c.get(&key)) {
+            return Ok(SpecificMetrics::analyzed(p, &name, metrics));
+        }
+        let contents = read()?;
+        Ok(match analyzer.analyze(path, contents.as_ref()) {
+            Some(metrics) => {
+                if let Some(cache) = cache {
+                    cache.insert(key, analyzer.name(), &metrics);
+                }
+                SpecificMetrics::analyzed(p, analyzer.name(), metrics)
+            }
+            None => SpecificMetrics::unanalyzed(p),
+        })
+    }
+
+    /// The name of the analyzer used for `path` and the metrics it produced.
+    pub fn measure(&self, path: &Path, contents: &[u8]) -> Option<(&str, FileMetrics)> {
+        let analyzer = self.analyzer_for(path)?;
+        let metrics = analyzer.analyze(path, contents)?;
+        Some((analyzer.name(), metrics))
+    }
+}
+
+impl Default for AnalyzerRegistry {
+    fn default() -> Self {
+        AnalyzerRegistry::with_builtins()
+    }
+}
+
+const RCA_ANALYZER: &str = "rust-code-analysis";
+const JAVA_ANALYZER: &str = "rust-code-analysis+heuristic";
+const HEURISTIC_ANALYZER: &str = "heuristic";
+
+/// Uses the rust-code-analysis grammar and metrics for `lang`.
+struct RcaAnalyzer {
+    lang: LANG,
+}
+
+impl LanguageAnalyzer for RcaAnalyzer {
+    fn name(&self) -> &str {
+        RCA_ANALYZER
+    }
+
+    fn supports_extension(&self, ext: &str) -> bool {
+        rust_code_analysis::get_from_ext(ext) == Some(self.lang)
+    }
+
+    fn analyze(&self, path: &Path, contents: &[u8]) -> Option<FileMetrics> {
+        let space = function_space(self.lang, contents.to_vec(), path)?;
+        let mut functions = vec![];
+        collect_functions(&space, &mut functions);
+        Some(FileMetrics {
+            loc: space.metrics.loc.lloc() as i64,
+            // The file's own space only counts code outside functions, the sums include every function.
+            cognitive: space.metrics.cognitive.cognitive_sum() as i64,
+            cyclomatic: space.metrics.cyclomatic.cyclomatic_sum() as i64,
+            functions,
+        })
+    }
+}
+
+/// rust-code-analysis 0.0.24 parses Java into function spaces but leaves its metrics at their defaults,
+/// so the grammar finds the functions and the line heuristic measures them. When the grammar fails the
+/// file is still measured by the heuristic, without its functions.
+struct JavaAnalyzer;
+
+impl LanguageAnalyzer for JavaAnalyzer {
+    fn name(&self) -> &str {
+        JAVA_ANALYZER
+    }
+
+    fn supports_extension(&self, ext: &str) -> bool {
+        ext == "java"
+    }
+
+    fn analyze(&self, path: &Path, contents: &[u8]) -> Option<FileMetrics> {
+        let mut functions = vec![];
+        if let Some(space) = function_space(LANG::Java, contents.to_vec(), path) {
+            let text = String::from_utf8_lossy(contents);
+            let lines: Vec<&str> = text.lines().collect();
+            collect_java_functions(&space, &lines, &mut functions);
+        }
+        let file = heuristic::measure(contents, Dialect::Java);
+        Some(FileMetrics {
+            loc: file.loc,
+            cognitive: file.cognitive,
+            cyclomatic: file.cyclomatic,
+            functions,
+        })
+    }
+}
+
+/// Measures every file with the line heuristic. Functions are not detected.
+struct HeuristicAnalyzer {
+    dialect: Dialect,
+}
+
+impl LanguageAnalyzer for HeuristicAnalyzer {
+    fn name(&self) -> &str {
+        HEURISTIC_ANALYZER
+    }
+
+    fn supports_extension(&self, ext: &str) -> bool {
+        Dialect::from_ext(ext) == Some(self.dialect)
+    }
+
+    fn analyze(&self, _path: &Path, contents: &[u8]) -> Option<FileMetrics> {
+        let m = heuristic::measure(contents, self.dialect);
+        Some(FileMetrics {
+            loc: m.loc,
+            cognitive: m.cognitive,
+            cyclomatic: m.cyclomatic,
+            functions: vec![],
+        })
+    }
+}
+
+fn function_space(lang: LANG, contents: Vec<u8>, path: &Path) -> Option<FuncSpace> {
+    let path_buf = path.to_path_buf();
+    match lang {
+        LANG::Rust => {
+            let parser = RustParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Javascript | LANG::Mozjs => {
+            let parser = JavascriptParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Typescript => {
+            let parser = TypescriptParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Tsx => {
+            let parser = TsxParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Python => {
+            let parser = PythonParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Cpp => {
+            let parser = CppParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        LANG::Java => {
+            let parser = JavaParser::new(contents, &path_buf, None);
+            rust_code_analysis::metrics(&parser, path)
+        }
+        _ => None,
+    }
+}
+
+fn collect_functions(space: &FuncSpace, functions: &mut Vec<FunctionMetrics>) {
+    for child in &space.spaces {
+        if child.kind == SpaceKind::Function {
+            functions.push(FunctionMetrics {
+                name: child
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| "<anonymous>".to_string()),
+                start_line: child.start_line,
+                end_line: child.end_line,
+                loc: child.metrics.loc.lloc() as i64,
+                cognitive: child.metrics.cognitive.cognitive() as i64,
+                cyclomatic: child.metrics.cyclomatic.cyclomatic() as i64,
+            });
+        }
+        collect_functions(child, functions);
+    }
+}
+
+/// Java spaces are all of kind `Unknown`, so a space is taken to be a method or constructor
+/// when its name is followed by a parameter list on its first line.
+fn collect_java_functions(space: &FuncSpace, lines: &[&str], functions: &mut Vec<FunctionMetrics>) {
+    for child in &space.spaces {
+        let first_line = lines.get(child.start_line.saturating_sub(1)).unwrap_or(&"");
+        let is_function = child.name.as_ref().is_some_and(|name| {
+            first_line
+                .split(name.as_str())
+                .skip(1)
+                .any(|rest| rest.trim_start().starts_with('('))
+        });
+        if is_function && child.end_line >= child.start_line {
+            let body = lines[child.start_line - 1..child.end_line.min(lines.len())].join("\n");
+            let m = heuristic::measure(body.as_bytes(), Dialect::Java);
+            functions.push(FunctionMetrics {
+                name: child.name.clone().unwrap_or_default(),
+                start_line: child.start_line,
+                end_line: child.end_line,
+                loc: m.loc,
+                cognitive: m.cognitive,
+                cyclomatic: m.cyclomatic,
+            });
+        }
+        collect_java_functions(child, lines, functions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzers::{AnalyzerRegistry, FileMetrics, LanguageAnalyzer};
+    use crate::cache::{clear, BlobCache};
+    use crate::test_support::temp_dir;
+    use std::path::Path;
+
+    struct Fixed;
+
+    impl LanguageAnalyzer for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+        fn supports_extension(&self, ext: &str) -> bool {
+            ext == "rs"
+        }
+        fn analyze(&self, _path: &Path, _contents: &[u8]) -> Option<FileMetrics> {
+            Some(FileMetrics {
+                loc: 42,
+                cognitive: 0,
+                cyclomatic: 1,
+                functions: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn unsupported_extension_has_no_metrics() {
+        let metrics = AnalyzerRegistry::default().analyze(Path::new("notes.txt"), b"hello");
+        assert!(metrics.analyzer.is_none());
+        assert!(metrics.loc.is_none());
+    }
+
+    #[test]
+    fn registered_analyzer_overrides_builtin() {
+        let mut registry = AnalyzerRegistry::with_builtins();
+        registry.register(Box::new(Fixed));
+        let metrics = registry.analyze(Path::new("main.rs"), b"fn main() {}");
+        assert_eq!(metrics.analyzer.as_deref(), Some("fixed"));
+        assert_eq!(metrics.loc, Some(42));
+    }
+
+    #[test]
+    fn cached_metrics_are_kept_apart_per_analyzer() {
+        let dir = temp_dir("analyzers");
+        let cache = BlobCache::open(&dir).unwrap();
+        let source = b"fn main() {}";
+        let builtin =
+            AnalyzerRegistry::default().analyze_cached(Path::new("main.rs"), source, Some(&cache));
+        assert_eq!(builtin.analyzer.as_deref(), Some("rust-code-analysis"));
+        let mut registry = AnalyzerRegistry::with_builtins();
+        registry.register(Box::new(Fixed));
+        let custom = registry.analyze_cached(Path::new("main.rs"), source, Some(&cache));
+        assert_eq!(custom.analyzer.as_deref(), Some("fixed"));
+        assert_eq!(custom.loc, Some(42));
+        clear(&dir).unwrap();
+    }
+
+    #[test]
+    fn java_methods_are_found_by_the_grammar() {
+        let source = b"class A {\n  int f(int x) {\n    if (x > 1) { return 2; }\n    return x;\n  }\n  void g() { }\n}\n";
+        let metrics = AnalyzerRegistry::default().analyze(Path::new("A.java"), source);
+        let names: Vec<&str> = metrics.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["f", "g"]);
+        assert_eq!(metrics.functions[0].start_line, 2);
+        assert_eq!(metrics.functions[0].cyclomatic, 2);
+    }
+
+    #[test]
+    fn rust_functions_are_listed() {
+        let source = b"fn a() {}\nfn b(x: bool) -> i32 {\n    if x { 1 } else { 2 }\n}\n";
+        let metrics = AnalyzerRegistry::default().analyze(Path::new("lib.rs"), source);
+        assert_eq!(metrics.analyzer.as_deref(), Some("rust-code-analysis"));
+        let names: Vec<&str> = metrics.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,210 @@
+//! A baseline records the rule violations a codebase already has, so `check` only fails on new ones.
+//!
+//! Violations are identified by rule, path and function name. Functions sharing a name in a file are
+//! matched in the order they appear. Line numbers are not part of the identity as they shift whenever
+//! code above them changes.
+
+use crate::check::Violation;
+use crate::config::RuleKind;
+use crate::error::HotspotError;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub rule: RuleKind,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    pub value: i64,
+}
+
+impl BaselineEntry {
+    fn key(&self) -> (RuleKind, String, Option<String>) {
+        (self.rule, self.path.clone(), self.function.clone())
+    }
+}
+
+impl From<&Violation> for BaselineEntry {
+    fn from(v: &Violation) -> Self {
+        BaselineEntry {
+            rule: v.rule,
+            path: v.path.to_string_lossy().to_string(),
+            function: v.function.clone(),
+            value: v.value,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub violations: Vec<BaselineEntry>,
+}
+
+/// A violation that is in the baseline with `was` as its value, or is new when `was` is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub violation: Violation,
+    pub was: Option<i64>,
+}
+
+/// How the current violations compare to a baseline.
+#[derive(Debug, Default)]
+pub struct BaselineComparison {
+    /// Violations that are new or worse than in the baseline.
+    pub regressions: Vec<Regression>,
+    /// Violations that are in the baseline and no worse.
+    pub known: usize,
+    /// Baseline entries that are no longer violated and can be removed from it.
+    pub fixed: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    pub fn from_violations(violations: &[Violation]) -> Self {
+        Baseline {
+            violations: violations.iter().map(BaselineEntry::from).collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, HotspotError> {
+        let text = fs::read_to_string(path).map_err(|e| HotspotError::io(path, e))?;
+        serde_json::from_str(&text).map_err(|e| {
+            HotspotError::InvalidConfig(format!("{}: not a baseline file: {}", path.display(), e))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), HotspotError> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| HotspotError::InvalidConfig(e.to_string()))?;
+        fs::write(path, text + "\n").map_err(|e| HotspotError::io(path, e))
+    }
+
+    /// The entries of the files `checked` returns true for, to compare a check of only some files with.
+    pub fn only(&self, checked: impl Fn(&str) -> bool) -> Self {
+        Baseline {
+            violations: self
+                .violations
+                .iter()
+                .filter(|e| checked(&e.path))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Replaces the entries of the files `checked` returns true for with `violations`, found by
+    /// checking those files, and keeps the entries of every other file.
+    pub fn update(&mut self, violations: &[Violation], checked: impl Fn(&str) -> bool) {
+        self.violations.retain(|e| !checked(&e.path));
+        self.violations
+            .extend(violations.iter().map(BaselineEntry::from));
+        // Stable, so the entries of a file stay in the order they were found in.
+        self.violations.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    /// Matches `violations`, ordered as [`crate::check::evaluate`] returns them, to the baseline.
+    pub fn compare(&self, violations: &[Violation]) -> BaselineComparison {
+        let mut baseline: HashMap<_, VecDeque<&BaselineEntry>> = HashMap::new();
+        for entry in &self.violations {
+            baseline.entry(entry.key()).or_default().push_back(entry);
+        }
+        let mut comparison = BaselineComparison::default();
+        for violation in violations {
+            let was = baseline
+                .get_mut(&BaselineEntry::from(violation).key())
+                .and_then(VecDeque::pop_front)
+                .map(|e| e.value);
+            let worse = |was: i64| {
+                if violation.rule.is_minimum() {
+                    violation.value < was
+                } else {
+                    violation.value > was
+                }
+            };
+            match was {
+                Some(was) if !worse(was) => comparison.known += 1,
+                was => comparison.regressions.push(Regression {
+                    violation: violation.clone(),
+                    was,
+                }),
+            }
+        }
+        comparison.fixed = baseline.into_values().flatten().cloned().collect();
+        comparison
+            .fixed
+            .sort_by(|a, b| (&a.path, &a.function).cmp(&(&b.path, &b.function)));
+        comparison
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::baseline::Baseline;
+    use crate::check::Violation;
+    use crate::config::{RuleKind, Severity};
+    use crate::shared_types::RawPath;
+
+    fn violation(path: &str, function: &str, line: usize, value: i64) -> Violation {
+        Violation {
+            rule: RuleKind::FunctionCognitive,
+            severity: Severity::Error,
+            path: RawPath::from(path),
+            function: Some(function.to_string()),
+            line: Some(line),
+            value,
+            limit: 25,
+        }
+    }
+
+    #[test]
+    fn only_new_and_worse_violations_are_regressions() {
+        let baseline = Baseline::from_violations(&[
+            violation("a.rs", "parse", 10, 30),
+            violation("a.rs", "render", 50, 40),
+            violation("b.rs", "run", 5, 26),
+        ]);
+        let current = vec![
+            // Moved down the file but no more complex.
+            violation("a.rs", "parse", 20, 30),
+            violation("a.rs", "render", 60, 45),
+            violation("c.rs", "load", 1, 27),
+        ];
+        let comparison = baseline.compare(&current);
+        let regressions: Vec<(String, Option<i64>)> = comparison
+            .regressions
+            .iter()
+            .map(|r| (r.violation.function.clone().unwrap(), r.was))
+            .collect();
+        assert_eq!(
+            regressions,
+            vec![("render".to_string(), Some(40)), ("load".to_string(), None)]
+        );
+        assert_eq!(comparison.known, 1);
+        assert_eq!(comparison.fixed.len(), 1);
+        assert_eq!(comparison.fixed[0].path, "b.rs");
+    }
+
+    #[test]
+    fn checking_some_files_leaves_the_others_in_the_baseline() {
+        let mut baseline = Baseline::from_violations(&[
+            violation("a.rs", "parse", 10, 30),
+            violation("b.rs", "run", 5, 26),
+            violation("c.rs", "load", 1, 27),
+        ]);
+        let checked = |path: &str| path == "a.rs" || path == "b.rs";
+        let current = vec![violation("a.rs", "parse", 10, 30)];
+        let comparison = baseline.only(checked).compare(&current);
+        assert_eq!(comparison.known, 1);
+        let fixed: Vec<&str> = comparison.fixed.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(fixed, vec!["b.rs"]);
+
+        baseline.update(&[violation("a.rs", "render", 60, 45)], checked);
+        let kept: Vec<(&str, Option<&str>)> = baseline
+            .violations
+            .iter()
+            .map(|e| (e.path.as_str(), e.function.as_deref()))
+            .collect();
+        assert_eq!(kept, vec![("a.rs", Some("render")), ("c.rs", Some("load"))]);
+    }
+}
@@ -0,0 +1,441 @@
+//! An on-disk cache under `.git/hotspot/` so repeated runs only process what changed.
+//!
+//! - `commits.jsonl` holds the diff summary of each commit keyed by commit id.
+//! - `blobs.jsonl` holds the metrics of each file's contents keyed by blob id, extension and
+//!   analyzer.
+//!
+//! Both files are append only, one JSON object per line. The `version` file records the version of the
+//! tool that wrote them and the whole cache is discarded when it does not match the running version.
+
+use crate::analyzers::{FileMetrics, FunctionMetrics};
+use crate::history::{CommitRecord, FileDelta};
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::{Delta, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The version of the tool plus a format number that is bumped when what is cached changes.
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+3");
+const VERSION_FILE: &str = "version";
+const COMMITS_FILE: &str = "commits.jsonl";
+const BLOBS_FILE: &str = "blobs.jsonl";
+
+/// The cache directory for `repo`. For a normal repository this is `.git/hotspot` and for a bare one
+/// `hotspot` inside it. Linked worktrees share the cache of the repository they were added to.
+pub fn cache_dir(repo: &Repository) -> PathBuf {
+    common_dir(repo).join("hotspot")
+}
+
+/// The git directory shared by all worktrees, named in the `commondir` file of a linked worktree.
+fn common_dir(repo: &Repository) -> PathBuf {
+    if repo.is_worktree() {
+        if let Ok(common) = fs::read_to_string(repo.path().join("commondir")) {
+            let common = repo.path().join(common.trim());
+            return common.canonicalize().unwrap_or(common);
+        }
+    }
+    repo.path().to_path_buf()
+}
+
+/// Creates `dir` if needed and empties it when it was written by a different version of the tool.
+fn prepare(dir: &Path) -> io::Result<()> {
+    let version = fs::read_to_string(dir.join(VERSION_FILE)).unwrap_or_default();
+    if version.trim() != VERSION {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(VERSION_FILE), VERSION)?;
+    }
+    Ok(())
+}
+
+fn read_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<Vec<T>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut entries = vec![];
+    for line in BufReader::new(file).lines() {
+        // A line that cannot be parsed (eg. an interrupted write) is skipped and will be recomputed.
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn append_lines<T: Serialize>(path: &Path, entries: &[T]) -> io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut buffer = String::new();
+    for entry in entries {
+        buffer.push_str(&serde_json::to_string(entry)?);
+        buffer.push('\n');
+    }
+    file.write_all(buffer.as_bytes())
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedCommit {
+    oid: String,
+    time: i64,
+    name: String,
+    email: String,
+    /// The name or email was not valid UTF-8 and has been decoded lossily.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    lossy: bool,
+    deltas: Vec<CachedDelta>,
+}
+
+/// Paths are written as text when they are valid UTF-8 and as an array of bytes otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CachedPath {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&RawPath> for CachedPath {
+    fn from(path: &RawPath) -> Self {
+        match std::str::from_utf8(path.as_bytes()) {
+            Ok(text) => CachedPath::Text(text.to_string()),
+            Err(_) => CachedPath::Bytes(path.as_bytes().to_vec()),
+        }
+    }
+}
+
+impl From<CachedPath> for RawPath {
+    fn from(path: CachedPath) -> Self {
+        match path {
+            CachedPath::Text(text) => RawPath::from(text.as_str()),
+            CachedPath::Bytes(bytes) => RawPath::from_bytes(&bytes),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedDelta {
+    path: CachedPath,
+    status: char,
+    additions: usize,
+    deletions: usize,
+}
+
+fn status_to_char(status: Delta) -> char {
+    match status {
+        Delta::Added => 'A',
+        Delta::Deleted => 'D',
+        Delta::Modified => 'M',
+        Delta::Renamed => 'R',
+        Delta::Copied => 'C',
+        Delta::Typechange => 'T',
+        Delta::Ignored => 'I',
+        Delta::Untracked => '?',
+        Delta::Unreadable => 'X',
+        Delta::Conflicted => 'U',
+        Delta::Unmodified => ' ',
+    }
+}
+
+fn char_to_status(status: char) -> Delta {
+    match status {
+        'A' => Delta::Added,
+        'D' => Delta::Deleted,
+        'M' => Delta::Modified,
+        'R' => Delta::Renamed,
+        'C' => Delta::Copied,
+        'T' => Delta::Typechange,
+        'I' => Delta::Ignored,
+        '?' => Delta::Untracked,
+        'X' => Delta::Unreadable,
+        'U' => Delta::Conflicted,
+        _ => Delta::Unmodified,
+    }
+}
+
+impl From<&CommitRecord> for CachedCommit {
+    fn from(record: &CommitRecord) -> Self {
+        CachedCommit {
+            oid: record.oid.to_string(),
+            time: record.time,
+            name: record.author.name().to_string(),
+            email: record.author.email().to_string(),
+            lossy: record.author.is_lossy(),
+            deltas: record
+                .deltas
+                .iter()
+                .map(|d| CachedDelta {
+                    path: CachedPath::from(&d.path),
+                    status: status_to_char(d.status),
+                    additions: d.additions,
+                    deletions: d.deletions,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl CachedCommit {
+    fn into_record(self) -> Option<CommitRecord> {
+        Some(CommitRecord {
+            oid: Oid::from_str(&self.oid).ok()?,
+            author: ContributorKey::new(self.email, self.name).lossy(self.lossy),
+            time: self.time,
+            deltas: self
+                .deltas
+                .into_iter()
+                .map(|d| FileDelta {
+                    path: d.path.into(),
+                    status: char_to_status(d.status),
+                    additions: d.additions,
+                    deletions: d.deletions,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// A cache holding entries added since it was opened until they are saved.
+pub trait Save {
+    /// Appends the new entries to the files of the cache.
+    fn save(&self) -> io::Result<()>;
+}
+
+/// Diff summaries of commits that have been walked before.
+pub struct CommitCache {
+    dir: PathBuf,
+    commits: HashMap<Oid, CommitRecord>,
+    pending: RefCell<HashMap<Oid, CommitRecord>>,
+}
+
+impl CommitCache {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        prepare(dir)?;
+        let commits = read_lines::<CachedCommit>(&dir.join(COMMITS_FILE))?
+            .into_iter()
+            .filter_map(CachedCommit::into_record)
+            .map(|r| (r.oid, r))
+            .collect();
+        Ok(CommitCache {
+            dir: dir.to_path_buf(),
+            commits,
+            pending: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// The commit as read from disk, or as walked since the cache was opened.
+    pub fn get(&self, oid: &Oid) -> Option<CommitRecord> {
+        match self.commits.get(oid) {
+            Some(record) => Some(record.clone()),
+            None => self.pending.borrow().get(oid).cloned(),
+        }
+    }
+
+    /// Records a newly walked commit. It is written to disk by [`CommitCache::save`].
+    pub fn insert(&self, record: &CommitRecord) {
+        self.pending.borrow_mut().insert(record.oid, record.clone());
+    }
+}
+
+impl Save for CommitCache {
+    fn save(&self) -> io::Result<()> {
+        let pending = self.pending.replace(HashMap::new());
+        let entries: Vec<CachedCommit> = pending.values().map(CachedCommit::from).collect();
+        append_lines(&self.dir.join(COMMITS_FILE), &entries)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedBlob {
+    key: String,
+    analyzer: String,
+    loc: i64,
+    cognitive: i64,
+    cyclomatic: i64,
+    functions: Vec<FunctionMetrics>,
+}
+
+/// Metrics of file contents that have been analyzed before. Safe to share between threads.
+pub struct BlobCache {
+    dir: PathBuf,
+    blobs: HashMap<String, (String, FileMetrics)>,
+    pending: Mutex<HashMap<String, CachedBlob>>,
+}
+
+impl BlobCache {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        prepare(dir)?;
+        let blobs = read_lines::<CachedBlob>(&dir.join(BLOBS_FILE))?
+            .into_iter()
+            .map(|b| {
+                let metrics = FileMetrics {
+                    loc: b.loc,
+                    cognitive: b.cognitive,
+                    cyclomatic: b.cyclomatic,
+                    functions: b.functions,
+                };
+                (b.key, (b.analyzer, metrics))
+            })
+            .collect();
+        Ok(BlobCache {
+            dir: dir.to_path_buf(),
+            blobs,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The key for the contents `oid` of a file with extension `ext` measured by the analyzer named
+    /// `analyzer`. The extension is part of the key as one analyzer can handle several languages.
+    pub fn key(oid: Oid, ext: &str, analyzer: &str) -> String {
+        format!("{}.{}.{}", oid, ext, analyzer)
+    }
+
+    /// The name of the analyzer and the metrics it produced, including those inserted since opening.
+    pub fn get(&self, key: &str) -> Option<(String, FileMetrics)> {
+        if let Some(found) = self.blobs.get(key) {
+            return Some(found.clone());
+        }
+        let pending = self.pending.lock().unwrap();
+        pending.get(key).map(|b| {
+            let metrics = FileMetrics {
+                loc: b.loc,
+                cognitive: b.cognitive,
+                cyclomatic: b.cyclomatic,
+                functions: b.functions.clone(),
+            };
+            (b.analyzer.clone(), metrics)
+        })
+    }
+
+    pub fn insert(&self, key: String, analyzer: &str, metrics: &FileMetrics) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.insert(
+            key.clone(),
+            CachedBlob {
+                key,
+                analyzer: analyzer.to_string(),
+                loc: metrics.loc,
+                cognitive: metrics.cognitive,
+                cyclomatic: metrics.cyclomatic,
+                functions: metrics.functions.clone(),
+            },
+        );
+    }
+}
+
+impl Save for BlobCache {
+    fn save(&self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let entries: Vec<CachedBlob> = pending.into_values().collect();
+        append_lines(&self.dir.join(BLOBS_FILE), &entries)
+    }
+}
+
+pub struct CacheStats {
+    pub version: Option<String>,
+    pub commits: usize,
+    pub blobs: usize,
+    pub bytes: u64,
+}
+
+/// Counts what is in the cache at `dir` without invalidating it.
+pub fn stats(dir: &Path) -> io::Result<CacheStats> {
+    let count = |file: &str| -> io::Result<usize> {
+        match File::open(dir.join(file)) {
+            Ok(f) => Ok(BufReader::new(f).lines().count()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    };
+    let mut bytes = 0;
+    if dir.exists() {
+        for entry in fs::read_dir(dir)? {
+            bytes += entry?.metadata()?.len();
+        }
+    }
+    Ok(CacheStats {
+        version: fs::read_to_string(dir.join(VERSION_FILE))
+            .ok()
+            .map(|v| v.trim().to_string()),
+        commits: count(COMMITS_FILE)?,
+        blobs: count(BLOBS_FILE)?,
+        bytes,
+    })
+}
+
+pub fn clear(dir: &Path) -> io::Result<()> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzers::FileMetrics;
+    use crate::cache::{clear, stats, BlobCache, CommitCache, Save, VERSION_FILE};
+    use crate::history::{self, HistoryFilter};
+    use crate::test_support::{commit, init_repo, temp_dir};
+    use git2::Oid;
+    use std::fs;
+
+    #[test]
+    fn blobs_survive_a_reopen_and_are_dropped_on_version_change() {
+        let dir = temp_dir("cache");
+        let key = BlobCache::key(Oid::zero(), "rs", "test");
+        let metrics = FileMetrics {
+            loc: 1,
+            cognitive: 0,
+            cyclomatic: 1,
+            functions: vec![],
+        };
+
+        let cache = BlobCache::open(&dir).unwrap();
+        assert!(cache.get(&key).is_none());
+        cache.insert(key.clone(), "test", &metrics);
+        cache.save().unwrap();
+
+        let cache = BlobCache::open(&dir).unwrap();
+        assert_eq!(cache.get(&key), Some(("test".to_string(), metrics)));
+        assert_eq!(stats(&dir).unwrap().blobs, 1);
+
+        fs::write(dir.join(VERSION_FILE), "0.0.0-old").unwrap();
+        let cache = BlobCache::open(&dir).unwrap();
+        assert!(cache.get(&key).is_none());
+        clear(&dir).unwrap();
+    }
+
+    #[test]
+    fn commits_walked_twice_are_cached_once() {
+        let (dir, repo) = init_repo("commit-cache");
+        commit(&repo, "a.rs", "fn a() {}\n", "Alice", 1_000);
+        commit(&repo, "b.rs", "fn b() {}\n", "Bob", 2_000);
+        commit(&repo, "a.rs", "fn a() { }\n", "Alice", 3_000);
+        let cache_dir = dir.join(".git").join("hotspot");
+
+        let cache = CommitCache::open(&cache_dir).unwrap();
+        for _ in 0..2 {
+            let walked = history::walk_from(&repo, "HEAD", &HistoryFilter::default())
+                .unwrap()
+                .with_cache(Some(&cache))
+                .count();
+            assert_eq!(walked, 3);
+        }
+        cache.save().unwrap();
+        assert_eq!(stats(&cache_dir).unwrap().commits, 3);
+
+        let cache = CommitCache::open(&cache_dir).unwrap();
+        let head = repo.head().unwrap().target().unwrap();
+        assert_eq!(cache.get(&head).unwrap().deltas.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
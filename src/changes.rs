@@ -0,0 +1,124 @@
+//! Finds the files a change touches, so reports can be limited to them (eg. in a pull request check)
+//! while history signals are still taken from the whole history.
+
+use crate::error::HotspotError;
+use crate::shared_types::RawPath;
+use git2::{Diff, DiffOptions, Repository, Tree};
+use std::collections::HashSet;
+
+/// What counts as the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeSet {
+    /// Commits on `HEAD` since it branched off the revision, like `git diff <revision>...HEAD`.
+    Since(String),
+    /// What is staged in the index compared to `HEAD`.
+    Staged,
+    /// Everything in the working tree that differs from `HEAD`, staged or not, including new files.
+    WorkingTree,
+}
+
+/// The paths, relative to the root of `repo`, that `changes` adds or modifies. Deleted files are
+/// left out as there is nothing left to measure.
+pub fn changed_files(
+    repo: &Repository,
+    changes: &ChangeSet,
+) -> Result<HashSet<RawPath>, HotspotError> {
+    let head = tree_of(repo, "HEAD")?;
+    let mut options = DiffOptions::new();
+    let diff = match changes {
+        ChangeSet::Since(revision) => {
+            let base = merge_base(repo, revision)?;
+            repo.diff_tree_to_tree(Some(&base), Some(&head), Some(&mut options))?
+        }
+        ChangeSet::Staged => repo.diff_tree_to_index(Some(&head), None, Some(&mut options))?,
+        ChangeSet::WorkingTree => {
+            options.include_untracked(true).recurse_untracked_dirs(true);
+            repo.diff_tree_to_workdir_with_index(Some(&head), Some(&mut options))?
+        }
+    };
+    Ok(paths(&diff))
+}
+
+fn paths(diff: &Diff) -> HashSet<RawPath> {
+    diff.deltas()
+        .filter(|d| d.status() != git2::Delta::Deleted)
+        .filter_map(|d| d.new_file().path_bytes())
+        .map(RawPath::from_bytes)
+        .collect()
+}
+
+fn tree_of<'repo>(repo: &'repo Repository, revision: &str) -> Result<Tree<'repo>, HotspotError> {
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|source| HotspotError::BadRevision {
+            revision: revision.to_string(),
+            source,
+        })
+}
+
+/// The tree of the commit `HEAD` and `revision` last had in common, or of `revision` itself when they
+/// share no history.
+fn merge_base<'repo>(repo: &'repo Repository, revision: &str) -> Result<Tree<'repo>, HotspotError> {
+    let bad_revision = |source| HotspotError::BadRevision {
+        revision: revision.to_string(),
+        source,
+    };
+    let base = repo
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(bad_revision)?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|source| HotspotError::BadRevision {
+            revision: "HEAD".to_string(),
+            source,
+        })?;
+    match repo.merge_base(base.id(), head.id()) {
+        Ok(oid) => Ok(repo.find_commit(oid)?.tree()?),
+        Err(_) => Ok(base.tree()?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::changes::{changed_files, ChangeSet};
+    use crate::shared_types::RawPath;
+    use crate::test_support::{commit, init_repo};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::Path;
+
+    fn paths(names: &[&str]) -> HashSet<RawPath> {
+        names.iter().map(|n| RawPath::from(*n)).collect()
+    }
+
+    #[test]
+    fn committed_staged_and_working_tree_changes_are_found() {
+        let (dir, repo) = init_repo("changes");
+        commit(&repo, "a.rs", "fn a() {}\n", "Alice", 1_000);
+        commit(&repo, "b.rs", "fn b() {}\n", "Alice", 1_000);
+        let base = repo
+            .head()
+            .unwrap()
+            .peel_to_commit()
+            .unwrap()
+            .id()
+            .to_string();
+        commit(&repo, "c.rs", "fn c() {}\n", "Alice", 1_000);
+        fs::write(dir.join("a.rs"), "fn a() { 1 }\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("b.rs"), "fn b() { 2 }\n").unwrap();
+        fs::write(dir.join("d.rs"), "fn d() {}\n").unwrap();
+
+        let since = changed_files(&repo, &ChangeSet::Since(base)).unwrap();
+        assert_eq!(since, paths(&["c.rs"]));
+        let staged = changed_files(&repo, &ChangeSet::Staged).unwrap();
+        assert_eq!(staged, paths(&["a.rs"]));
+        let working = changed_files(&repo, &ChangeSet::WorkingTree).unwrap();
+        assert_eq!(working, paths(&["a.rs", "b.rs", "d.rs"]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Evaluates the rules of a [`ProjectConfig`](crate::config::ProjectConfig) against the metrics and
+//! history of a revision.
+
+use crate::activity::Activity;
+use crate::config::{Rule, RuleKind, Severity};
+use crate::error::HotspotError;
+use crate::hotspots::Hotspot;
+use crate::shared_types::{RawPath, SpecificMetrics};
+
+/// What the rules are checked against. Paths are relative to the root of the repository.
+pub struct CheckInput<'a> {
+    pub metrics: &'a [SpecificMetrics],
+    pub hotspots: &'a [Hotspot],
+    pub activity: &'a Activity,
+}
+
+/// A file, or function in it, that breaks a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: RuleKind,
+    pub severity: Severity,
+    pub path: RawPath,
+    /// The function name for the function rules.
+    pub function: Option<String>,
+    /// Where the function starts, `None` for the file rules.
+    pub line: Option<usize>,
+    pub value: i64,
+    pub limit: i64,
+}
+
+impl Violation {
+    /// Describes the violation in a sentence, eg. for SARIF results.
+    pub fn message(&self) -> String {
+        let subject = match &self.function {
+            Some(name) => format!("Function '{}'", name),
+            None => "File".to_string(),
+        };
+        let measure = match self.rule {
+            RuleKind::FunctionCognitive | RuleKind::FileCognitive => "cognitive complexity",
+            RuleKind::FunctionCyclomatic => "cyclomatic complexity",
+            RuleKind::FileLoc => "lines of code",
+            RuleKind::HotspotScore => "hotspot score",
+            RuleKind::BusFactor => "bus factor",
+        };
+        let comparison = if self.rule.is_minimum() {
+            "below the minimum of"
+        } else {
+            "above the maximum of"
+        };
+        format!(
+            "{}: {} is {}, {} {}",
+            subject, measure, self.value, comparison, self.limit
+        )
+    }
+}
+
+/// Every violation of `rules`, ordered by path and then line.
+pub fn evaluate(rules: &[Rule], input: &CheckInput) -> Result<Vec<Violation>, HotspotError> {
+    let mut violations = vec![];
+    for rule in rules {
+        let matcher = rule.path_matcher()?;
+        let applies = |path: &RawPath| {
+            matcher
+                .as_ref()
+                .is_none_or(|m| m.is_match(path.to_string_lossy()))
+        };
+        let violation = |path: &RawPath, function: Option<(&str, usize)>, value: i64| Violation {
+            rule: rule.kind,
+            severity: rule.severity,
+            path: path.clone(),
+            function: function.map(|f| f.0.to_string()),
+            line: function.map(|f| f.1),
+            value,
+            limit: rule.limit(),
+        };
+        for file in input.metrics.iter().filter(|m| applies(&m.path)) {
+            let file_value = match rule.kind {
+                RuleKind::FileCognitive => file.cognitive,
+                RuleKind::FileLoc => file.loc,
+                RuleKind::BusFactor => input
+                    .activity
+                    .files
+                    .get(&file.path)
+                    .map(|f| f.contributors.len() as i64)
+                    .or(Some(0)),
+                _ => None,
+            };
+            if let Some(value) = file_value.filter(|v| rule.is_violated_by(*v)) {
+                violations.push(violation(&file.path, None, value));
+            }
+            for function in &file.functions {
+                let value = match rule.kind {
+                    RuleKind::FunctionCognitive => function.cognitive,
+                    RuleKind::FunctionCyclomatic => function.cyclomatic,
+                    _ => continue,
+                };
+                if rule.is_violated_by(value) {
+                    let at = Some((function.name.as_str(), function.start_line));
+                    violations.push(violation(&file.path, at, value));
+                }
+            }
+        }
+        if rule.kind == RuleKind::HotspotScore {
+            for hotspot in input.hotspots.iter().filter(|h| applies(&h.path)) {
+                if rule.is_violated_by(hotspot.score) {
+                    violations.push(violation(&hotspot.path, None, hotspot.score));
+                }
+            }
+        }
+    }
+    violations.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::analyzers::{FileMetrics, FunctionMetrics};
+    use crate::check::{evaluate, CheckInput};
+    use crate::config::{Rule, RuleKind};
+    use crate::hotspots::Hotspot;
+    use crate::shared_types::{RawPath, SpecificMetrics};
+    use crate::test_support::record;
+
+    fn file(path: &str, cognitive: i64) -> SpecificMetrics {
+        let function = FunctionMetrics {
+            name: "run".to_string(),
+            start_line: 3,
+            end_line: 30,
+            loc: 20,
+            cognitive,
+            cyclomatic: 5,
+        };
+        let metrics = FileMetrics {
+            loc: 40,
+            cognitive,
+            cyclomatic: 5,
+            functions: vec![function],
+        };
+        SpecificMetrics::analyzed(RawPath::from(path), "test", metrics)
+    }
+
+    #[test]
+    fn each_kind_of_rule_reports_what_breaks_it() {
+        let metrics = vec![file("src/core/engine.rs", 30), file("src/ui.rs", 10)];
+        let hotspots = vec![Hotspot {
+            path: RawPath::from("src/ui.rs"),
+            touches: 50,
+            cognitive: 10,
+            loc: 40,
+            score: 500,
+        }];
+        let mut activity = Activity::default();
+        activity.add(record("alice", 0, &["src/core/engine.rs"]));
+        let mut bus_factor = Rule::new(RuleKind::BusFactor, 2);
+        bus_factor.paths = vec!["src/core/**".to_string()];
+        let rules = vec![
+            Rule::new(RuleKind::FunctionCognitive, 25),
+            Rule::new(RuleKind::HotspotScore, 100),
+            bus_factor,
+        ];
+        let input = CheckInput {
+            metrics: &metrics,
+            hotspots: &hotspots,
+            activity: &activity,
+        };
+        let found: Vec<(RuleKind, String, Option<usize>, i64)> = evaluate(&rules, &input)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.rule, v.path.to_string(), v.line, v.value))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    RuleKind::BusFactor,
+                    "src/core/engine.rs".to_string(),
+                    None,
+                    1
+                ),
+                (
+                    RuleKind::FunctionCognitive,
+                    "src/core/engine.rs".to_string(),
+                    Some(3),
+                    30
+                ),
+                (RuleKind::HotspotScore, "src/ui.rs".to_string(), None, 500),
+            ]
+        );
+    }
+}
@@ -1,28 +1,55 @@
 extern crate clap;
 
+use chrono::NaiveDate;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use hotspot::changes::ChangeSet;
+use hotspot::codeowners::DriftLimits;
+use hotspot::error::HotspotError;
+use hotspot::history::HistoryFilter;
+use hotspot::rollup::Grouping;
 use hotspot::shared_types::{
-    BusFactorConfig, ContributorsConfig, HottestConfig, MetricsConfig, Verbosity,
+    BusFactorConfig, CacheAction, CacheConfig, CheckConfig, ComponentsConfig, ContributorsConfig,
+    DiffConfig, HottestConfig, KnowledgeConfig, MetricsConfig, OutputFormat, ReportConfig,
+    TrendConfig, Verbosity,
 };
+use hotspot::table::Terminal;
+use hotspot::trend::Interval;
 use path_absolutize::Absolutize;
 use std::env;
 use std::path::Path;
+use std::str::FromStr;
 
 pub enum CliCommand {
     About,
     BusFactor(BusFactorConfig),
+    Cache(CacheConfig),
+    Check(CheckConfig),
+    Components(ComponentsConfig),
     Contributors(ContributorsConfig),
+    Diff(DiffConfig),
     Metrics(MetricsConfig),
     Hottest(HottestConfig),
+    Knowledge(KnowledgeConfig),
+    Report(ReportConfig),
+    Trend(TrendConfig),
     //Recommend,
     Nothing,
 }
 
 const ABOUT_CMD: &str = "about";
 const BUSFACTOR_CMD: &str = "busfactor";
+const CACHE_CMD: &str = "cache";
+const CACHE_CLEAR_CMD: &str = "clear";
+const CACHE_STATS_CMD: &str = "stats";
+const CHECK_CMD: &str = "check";
+const COMPONENTS_CMD: &str = "components";
 const CONTRIBUTOR_CMD: &str = "contributors";
+const DIFF_CMD: &str = "diff";
+const KNOWLEDGE_CMD: &str = "knowledge";
 const METRICS_CMD: &str = "metrics";
 const HOTTEST_CMD: &str = "hottest";
+const REPORT_CMD: &str = "report";
+const TREND_CMD: &str = "trend";
 
 pub fn capture_input() -> App<'static, 'static> {
     // NOTE: Setting Arg::default_value effectively disables this option as it will ensure that some argument is always present.
@@ -45,15 +72,27 @@ pub fn capture_input() -> App<'static, 'static> {
                 .short("s")
                 .help("Sets to silent mode"),
         )
+        // FLAG: DISABLE CACHE
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .help("Neither reads nor writes the analysis cache in .git/hotspot"),
+        )
+        // FLAG: DISABLE COLOUR
+        .arg(
+            Arg::with_name("no-color")
+                .long("no-color")
+                .help("Prints tables without colour, as does setting the NO_COLOR environment variable"),
+        )
         // OPTION: CONFIG FILE
-        // .arg(
-        //     Arg::with_name("config")
-        //         .short("c")
-        //         .long("config")
-        //         .value_name("CONFIG_FILE")
-        //         .help("Sets a custom config file")
-        //         .takes_value(true),
-        // )
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("CONFIG_FILE")
+                .help("Sets a custom config file (default is .hotspot.toml in the repository root)")
+                .takes_value(true),
+        )
         // OPTION: OUTPUT FILE
         // .arg(
         //     Arg::with_name("output")
@@ -92,8 +131,139 @@ pub fn capture_input() -> App<'static, 'static> {
                         .required(true)
                         .default_value("./")
                         .index(1),
+                )
+                .args(&history_filter_args())
+                .arg(recurse_submodules_arg())
+                .args(&changed_files_args())
+                .args(&group_by_args())
+                .arg(
+                    Arg::with_name("OWNERS")
+                        .help("Compares who changes each file to its owners in CODEOWNERS, flagging drifted files")
+                        .long("owners")
+                        .conflicts_with("GROUP_BY"),
+                )
+                .arg(by_arg())
+                .arg(
+                    Arg::with_name("INACTIVE_MONTHS")
+                        .help("Flags files none of whose owners changed them in this many months [default: 6]")
+                        .long("inactive-months")
+                        .value_name("MONTHS")
+                        .takes_value(true)
+                        .requires("OWNERS"),
+                )
+                .arg(
+                    Arg::with_name("CHURN")
+                        .help("Flags files without an owner changed at least this many times [default: 10]")
+                        .long("churn")
+                        .value_name("CHANGES")
+                        .takes_value(true)
+                        .requires("OWNERS"),
+                )
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
+        )
+        // COMMAND: CACHE
+        .subcommand(
+            SubCommand::with_name(CACHE_CMD)
+                .about("Manages the analysis cache kept in .git/hotspot")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name(CACHE_CLEAR_CMD)
+                        .about("Deletes the cache")
+                        // ARG: SOURCE CODE REPOSITORY
+                        .arg(
+                            Arg::with_name("SOURCE")
+                                .help("Sets the input path of source code to use")
+                                .required(true)
+                                .default_value("./")
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name(CACHE_STATS_CMD)
+                        .about("Shows what the cache contains")
+                        // ARG: SOURCE CODE REPOSITORY
+                        .arg(
+                            Arg::with_name("SOURCE")
+                                .help("Sets the input path of source code to use")
+                                .required(true)
+                                .default_value("./")
+                                .index(1),
+                        ),
                 ),
         )
+        // COMMAND: CHECK
+        .subcommand(
+            SubCommand::with_name(CHECK_CMD)
+                .about("Checks the repository against the rules in the config file and fails on violations")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Checks the files committed at this revision (commit, tag or branch)")
+                        .default_value("HEAD")
+                        .long("rev"),
+                )
+                .arg(
+                    Arg::with_name("BASELINE")
+                        .help("Only fails on violations that are not in this baseline file or got worse")
+                        .long("baseline")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("WRITE_BASELINE")
+                        .help("Records the current violations in the --baseline file and succeeds")
+                        .long("write-baseline")
+                        .requires("BASELINE"),
+                )
+                .args(&changed_files_args())
+                .arg(format_arg(&["table", "sarif", "markdown"]))
+                .arg(collapse_after_arg())
+                .arg(
+                    Arg::with_name("TOP")
+                        .help("Sets how many of the highest scoring files are reported as hotspots in SARIF output")
+                        .required(false)
+                        .default_value("10")
+                        .long("top"),
+                )
+                .arg(jobs_arg()),
+        )
+        // COMMAND: COMPONENTS
+        .subcommand(
+            SubCommand::with_name(COMPONENTS_CMD)
+                .about("Reports on each component set in the config file and which components change together")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Reports on the files committed at this revision (commit, tag or branch)")
+                        .default_value("HEAD")
+                        .long("rev"),
+                )
+                .args(&history_filter_args())
+                .arg(jobs_arg())
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
+        )
         // COMMAND: CONTRIBUTOR
         .subcommand(
             SubCommand::with_name(CONTRIBUTOR_CMD)
@@ -107,7 +277,50 @@ pub fn capture_input() -> App<'static, 'static> {
                         .required(true)
                         .default_value("./")
                         .index(1),
-                ),
+                )
+                .args(&history_filter_args())
+                .arg(recurse_submodules_arg())
+                .arg(by_arg())
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
+        )
+        // COMMAND: DIFF
+        .subcommand(
+            SubCommand::with_name(DIFF_CMD)
+                .about("Compares the metrics and hotspots of two revisions")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                .arg(
+                    Arg::with_name("BASE")
+                        .help("The revision to compare from, eg. the branch being merged into")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("HEAD")
+                        .help("The revision to compare to")
+                        .required(true)
+                        .default_value("HEAD")
+                        .index(2),
+                )
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(3),
+                )
+                .arg(
+                    Arg::with_name("TOP")
+                        .help("Sets how many of the highest scoring files count as hotspots")
+                        .required(false)
+                        .default_value("10")
+                        .long("top"),
+                )
+                .arg(jobs_arg())
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
         )
         // COMMAND: HOTTEST
         .subcommand(
@@ -123,13 +336,60 @@ pub fn capture_input() -> App<'static, 'static> {
                         .default_value("./")
                         .index(1),
                 )
+                .args(&history_filter_args())
+                .arg(recurse_submodules_arg())
+                .args(&changed_files_args())
                 .arg(
                     Arg::with_name("TOP")
-                        .help("Sets the number on how many results are returned. '0' returns all.")
+                        .help("Sets the number on how many results are returned. '0' returns all, or 10 in SARIF output.")
                         .required(false)
                         .default_value("0")
                         .long("top"),
-                ),
+                )
+                .args(&group_by_args())
+                .arg(format_arg(&["table", "sarif", "markdown"]))
+                .arg(collapse_after_arg()),
+        )
+        // COMMAND: KNOWLEDGE
+        .subcommand(
+            SubCommand::with_name(KNOWLEDGE_CMD)
+                .about("Finds the files mostly changed by people who have left, ranked by complexity and churn")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("DEPARTED")
+                        .help("Counts the people with this email or name as having left. Can be repeated.")
+                        .long("departed")
+                        .value_name("PERSON")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("INACTIVE_MONTHS")
+                        .help("Counts anyone who has not committed in this many months as having left")
+                        .long("inactive-months")
+                        .value_name("MONTHS")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Reports on the files committed at this revision (commit, tag or branch)")
+                        .default_value("HEAD")
+                        .long("rev"),
+                )
+                .arg(jobs_arg())
+                .args(&group_by_args())
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
         )
         // COMMAND: METRICS
         .subcommand(
@@ -144,11 +404,295 @@ pub fn capture_input() -> App<'static, 'static> {
                         .required(true)
                         .default_value("./")
                         .index(1),
-                ),
+                )
+                .arg(jobs_arg())
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Measures the files committed at this revision (commit, tag or branch) without checking it out")
+                        .long("rev")
+                        .takes_value(true),
+                )
+                .args(&changed_files_args())
+                .args(&group_by_args())
+                .arg(format_arg(&["table", "sarif", "svg", "markdown"]))
+                .arg(
+                    Arg::with_name("TOP")
+                        .help("Sets how many of the highest scoring files are reported as hotspots in SARIF output")
+                        .required(false)
+                        .default_value("10")
+                        .long("top"),
+                )
+                .arg(collapse_after_arg()),
+        )
+        // COMMAND: REPORT
+        .subcommand(
+            SubCommand::with_name(REPORT_CMD)
+                .about("Writes the metrics, hottest, busfactor and contributors reports to one HTML file")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("HTML")
+                        .help("Writes a self-contained HTML page with sortable tables to this file")
+                        .long("html")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("REV")
+                        .help("Reports on the files committed at this revision (commit, tag or branch)")
+                        .default_value("HEAD")
+                        .long("rev"),
+                )
+                .args(&history_filter_args())
+                .arg(jobs_arg()),
+        )
+        // COMMAND: TREND
+        .subcommand(
+            SubCommand::with_name(TREND_CMD)
+                .about("Measures complexity at points in history to show how it changes over time")
+                .version("0.1")
+                .author("Devon B. <devon@chimplab.co>")
+                // ARG: SOURCE CODE REPOSITORY
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("Sets the input path of source code to use")
+                        .required(true)
+                        .default_value("./")
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("EVERY")
+                        .help("Samples every N commits, counting back from HEAD")
+                        .long("every")
+                        .takes_value(true)
+                        .conflicts_with_all(&["WEEKLY", "TAGS"]),
+                )
+                .arg(
+                    Arg::with_name("WEEKLY")
+                        .help("Samples the last commit of each Monday to Sunday week in UTC (the default)")
+                        .long("weekly")
+                        .conflicts_with("TAGS"),
+                )
+                .arg(
+                    Arg::with_name("TAGS")
+                        .help("Samples every tag")
+                        .long("tags"),
+                )
+                .arg(
+                    Arg::with_name("PER_FILE")
+                        .help("Also lists the metrics of every file at every sample")
+                        .long("per-file"),
+                )
+                .arg(jobs_arg())
+                .arg(format_arg(&["table", "markdown"]))
+                .arg(collapse_after_arg()),
         );
     app
 }
 
+fn history_filter_args() -> [Arg<'static, 'static>; 3] {
+    [
+        Arg::with_name("SINCE")
+            .help("Only include commits on or after this date (YYYY-MM-DD)")
+            .long("since")
+            .takes_value(true)
+            .validator(|d| parse_date(&d).map(|_| ())),
+        Arg::with_name("UNTIL")
+            .help("Only include commits on or before this date (YYYY-MM-DD)")
+            .long("until")
+            .takes_value(true)
+            .validator(|d| parse_date(&d).map(|_| ())),
+        Arg::with_name("AUTHOR")
+            .help("Only include commits by this author name or email. Can be repeated.")
+            .long("author")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
+
+fn recurse_submodules_arg() -> Arg<'static, 'static> {
+    Arg::with_name("RECURSE_SUBMODULES")
+        .help("Also includes the history of checked out submodules, prefixed with their path")
+        .long("recurse-submodules")
+}
+
+fn changed_files_args() -> [Arg<'static, 'static>; 3] {
+    [
+        Arg::with_name("CHANGED_SINCE")
+            .help("Only reports files changed on HEAD since it branched off this revision")
+            .long("changed-since")
+            .value_name("REV")
+            .takes_value(true)
+            .conflicts_with_all(&["STAGED", "WORKING_TREE"]),
+        Arg::with_name("STAGED")
+            .help("Only reports files with staged changes")
+            .long("staged")
+            .conflicts_with("WORKING_TREE"),
+        Arg::with_name("WORKING_TREE")
+            .help("Only reports files changed in the working tree, staged or not")
+            .long("working-tree"),
+    ]
+}
+
+fn jobs_arg() -> Arg<'static, 'static> {
+    Arg::with_name("JOBS")
+        .help("Sets how many files are parsed in parallel. '0' uses one per CPU.")
+        .required(false)
+        .default_value("0")
+        .short("j")
+        .long("jobs")
+}
+
+fn format_arg(formats: &'static [&'static str]) -> Arg<'static, 'static> {
+    Arg::with_name("FORMAT")
+        .help("Sets how the report is printed")
+        .long("format")
+        .takes_value(true)
+        .possible_values(formats)
+        .default_value("table")
+}
+
+fn group_by_args() -> [Arg<'static, 'static>; 3] {
+    [
+        Arg::with_name("GROUP_BY")
+            .help("Rolls the report up into groups of files instead of listing every file")
+            .long("group-by")
+            .takes_value(true)
+            .possible_values(&["dir", "component"]),
+        Arg::with_name("DEPTH")
+            .help("Sets how many directories down files are grouped [default: 1]")
+            .long("depth")
+            .takes_value(true)
+            .requires("GROUP_BY"),
+        Arg::with_name("TREE")
+            .help("Shows the groups as a tree, each directory rolled up into the ones above it")
+            .long("tree")
+            .requires("GROUP_BY"),
+    ]
+}
+
+fn grouping(input: &ArgMatches) -> Result<Option<Grouping>, HotspotError> {
+    if !input.is_present("GROUP_BY") {
+        return Ok(None);
+    }
+    if input.value_of("GROUP_BY") == Some("component") {
+        if input.is_present("DEPTH") || input.is_present("TREE") {
+            return Err(HotspotError::InvalidConfig(
+                "--depth and --tree only work with --group-by dir".to_string(),
+            ));
+        }
+        return Ok(Some(Grouping::Component));
+    }
+    let depth = if input.is_present("DEPTH") {
+        parse_number(input, "DEPTH")?
+    } else {
+        1
+    };
+    Ok(Some(Grouping::Directory {
+        depth,
+        tree: input.is_present("TREE"),
+    }))
+}
+
+fn by_arg() -> Arg<'static, 'static> {
+    Arg::with_name("BY")
+        .help("Counts people, or the teams set in the config file")
+        .long("by")
+        .value_name("WHO")
+        .takes_value(true)
+        .possible_values(&["person", "team"])
+}
+
+fn drift_limits(input: &ArgMatches) -> Result<Option<DriftLimits>, HotspotError> {
+    if !input.is_present("OWNERS") {
+        return Ok(None);
+    }
+    Ok(Some(DriftLimits {
+        inactive_months: if input.is_present("INACTIVE_MONTHS") {
+            parse_number(input, "INACTIVE_MONTHS")?
+        } else {
+            6
+        },
+        churn: if input.is_present("CHURN") {
+            parse_number(input, "CHURN")?
+        } else {
+            10
+        },
+    }))
+}
+
+fn collapse_after_arg() -> Arg<'static, 'static> {
+    Arg::with_name("COLLAPSE_AFTER")
+        .help("Puts the rows of Markdown tables after the first ROWS in a collapsed <details> section")
+        .long("collapse-after")
+        .value_name("ROWS")
+        .takes_value(true)
+}
+
+fn output_format(args: &ArgMatches, input: &ArgMatches) -> Result<OutputFormat, HotspotError> {
+    Ok(match input.value_of("FORMAT") {
+        Some("sarif") => OutputFormat::Sarif,
+        Some("svg") => OutputFormat::Svg,
+        Some("markdown") => OutputFormat::Markdown {
+            collapse_after: if input.is_present("COLLAPSE_AFTER") {
+                Some(parse_number(input, "COLLAPSE_AFTER")?)
+            } else {
+                None
+            },
+        },
+        _ => OutputFormat::Table(Terminal::detect(args.is_present("no-color"))),
+    })
+}
+
+fn parse_date(date: &str) -> Result<i64, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+        .map_err(|e| format!("'{}' is not a YYYY-MM-DD date: {}", date, e))
+}
+
+const SECONDS_IN_DAY: i64 = 24 * 60 * 60;
+
+fn history_filter(input: &ArgMatches) -> Result<HistoryFilter, HotspotError> {
+    let date = |name: &str| {
+        input
+            .value_of(name)
+            .map(parse_date)
+            .transpose()
+            .map_err(HotspotError::InvalidConfig)
+    };
+    Ok(HistoryFilter {
+        since: date("SINCE")?,
+        // The until date is inclusive so the window ends at the start of the following day.
+        until: date("UNTIL")?.map(|d| d + SECONDS_IN_DAY),
+        authors: input
+            .values_of("AUTHOR")
+            .map(|a| a.map(String::from).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn changes(input: &ArgMatches) -> Option<ChangeSet> {
+    if let Some(revision) = input.value_of("CHANGED_SINCE") {
+        Some(ChangeSet::Since(revision.to_string()))
+    } else if input.is_present("STAGED") {
+        Some(ChangeSet::Staged)
+    } else if input.is_present("WORKING_TREE") {
+        Some(ChangeSet::WorkingTree)
+    } else {
+        None
+    }
+}
+
 fn verbosity(input: &ArgMatches) -> Verbosity {
     let occurrences = input.occurrences_of("verbosity");
     let verbosity: Verbosity = match occurrences {
@@ -167,62 +711,257 @@ fn verbosity(input: &ArgMatches) -> Verbosity {
     verbosity
 }
 
-pub fn repository_path(source: Option<&str>) -> String {
-    let current_dir = env::current_dir().unwrap();
-    match source {
+pub fn repository_path(source: Option<&str>) -> Result<String, HotspotError> {
+    let current_dir = env::current_dir().map_err(|e| HotspotError::io(Path::new("."), e))?;
+    let display_path = match source {
         Some(s) => {
             let base_dir = Path::new(s);
-            let display_path = if base_dir.is_absolute() {
+            if base_dir.is_absolute() {
                 base_dir.to_path_buf()
             } else {
                 current_dir.join(base_dir)
-            };
-            String::from(display_path.absolutize().unwrap().to_str().unwrap())
+            }
         }
-        None => String::from(current_dir.absolutize().unwrap().to_str().unwrap()),
-    }
+        None => current_dir,
+    };
+    let absolute = display_path
+        .absolutize()
+        .map_err(|e| HotspotError::io(&display_path, e))?;
+    Ok(absolute.to_string_lossy().to_string())
+}
+
+fn parse_number<T: FromStr>(input: &ArgMatches, name: &str) -> Result<T, HotspotError> {
+    let value = input.value_of(name).unwrap_or_default();
+    value.parse().map_err(|_| {
+        HotspotError::InvalidConfig(format!("'{}' is not a valid number for {}", value, name))
+    })
 }
 
 /// Parses the command line arguments into the correct config object for the passed in command
-pub fn parse(arg_matches: ArgMatches) -> CliCommand {
-    if arg_matches.subcommand_matches(ABOUT_CMD).is_some() {
+pub fn parse(arg_matches: ArgMatches) -> Result<CliCommand, HotspotError> {
+    let command = if arg_matches.subcommand_matches(ABOUT_CMD).is_some() {
         CliCommand::About
+    } else if arg_matches.subcommand_matches(CHECK_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(CHECK_CMD).unwrap();
+        CliCommand::Check(CheckConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            revision: cmd_matches.value_of("REV").unwrap_or("HEAD").to_string(),
+            config_path: arg_matches.value_of("config").map(String::from),
+            baseline: cmd_matches.value_of("BASELINE").map(String::from),
+            write_baseline: cmd_matches.is_present("WRITE_BASELINE"),
+            format: output_format(&arg_matches, cmd_matches)?,
+            top: parse_number(cmd_matches, "TOP")?,
+            changes: changes(cmd_matches),
+        })
+    } else if arg_matches.subcommand_matches(COMPONENTS_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(COMPONENTS_CMD).unwrap();
+        CliCommand::Components(ComponentsConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            revision: cmd_matches.value_of("REV").unwrap_or("HEAD").to_string(),
+            history: history_filter(cmd_matches)?,
+            config_path: arg_matches.value_of("config").map(String::from),
+            format: output_format(&arg_matches, cmd_matches)?,
+        })
     } else if arg_matches.subcommand_matches(CONTRIBUTOR_CMD).is_some() {
         let cmd_matches = arg_matches.subcommand_matches(CONTRIBUTOR_CMD).unwrap();
         CliCommand::Contributors(ContributorsConfig {
-            repository_path: repository_path(cmd_matches.value_of("SOURCE")),
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
             verbosity: verbosity(&arg_matches),
             output: "".to_string(),
             includes: "".to_string(),
             excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            history: history_filter(cmd_matches)?,
+            recurse_submodules: cmd_matches.is_present("RECURSE_SUBMODULES"),
+            format: output_format(&arg_matches, cmd_matches)?,
+            by_team: cmd_matches.value_of("BY") == Some("team"),
+            config_path: arg_matches.value_of("config").map(String::from),
+        })
+    } else if arg_matches.subcommand_matches(DIFF_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(DIFF_CMD).unwrap();
+        CliCommand::Diff(DiffConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            base: cmd_matches.value_of("BASE").unwrap_or_default().to_string(),
+            head: cmd_matches.value_of("HEAD").unwrap_or("HEAD").to_string(),
+            top: parse_number(cmd_matches, "TOP")?,
+            format: output_format(&arg_matches, cmd_matches)?,
+        })
+    } else if arg_matches.subcommand_matches(CACHE_CMD).is_some() {
+        let cache_matches = arg_matches.subcommand_matches(CACHE_CMD).unwrap();
+        let (action, cmd_matches) = match cache_matches.subcommand() {
+            (CACHE_CLEAR_CMD, Some(m)) => (CacheAction::Clear, m),
+            (_, Some(m)) => (CacheAction::Stats, m),
+            _ => return Ok(CliCommand::Nothing),
+        };
+        CliCommand::Cache(CacheConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            action,
         })
     } else if arg_matches.subcommand_matches(BUSFACTOR_CMD).is_some() {
         let cmd_matches = arg_matches.subcommand_matches(BUSFACTOR_CMD).unwrap();
+        let group_by = grouping(cmd_matches)?;
+        let owners = drift_limits(cmd_matches)?;
+        // Groups and owners are compared by person, counting teams there would need its own report.
+        let by_team = cmd_matches.value_of("BY") == Some("team");
+        if by_team && (group_by.is_some() || owners.is_some()) {
+            return Err(HotspotError::InvalidConfig(
+                "--by team cannot be combined with --group-by or --owners".to_string(),
+            ));
+        }
         CliCommand::BusFactor(BusFactorConfig {
-            repository_path: repository_path(cmd_matches.value_of("SOURCE")),
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
             verbosity: verbosity(&arg_matches),
             output: "".to_string(),
             includes: "".to_string(),
             excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            history: history_filter(cmd_matches)?,
+            recurse_submodules: cmd_matches.is_present("RECURSE_SUBMODULES"),
+            changes: changes(cmd_matches),
+            format: output_format(&arg_matches, cmd_matches)?,
+            group_by,
+            config_path: arg_matches.value_of("config").map(String::from),
+            owners,
+            by_team,
         })
     } else if arg_matches.subcommand_matches(HOTTEST_CMD).is_some() {
         let cmd_matches = arg_matches.subcommand_matches(HOTTEST_CMD).unwrap();
+        let format = output_format(&arg_matches, cmd_matches)?;
+        let group_by = grouping(cmd_matches)?;
+        if group_by.is_some() && matches!(format, OutputFormat::Sarif) {
+            return Err(HotspotError::InvalidConfig(
+                "--group-by only works with table and markdown output".to_string(),
+            ));
+        }
         CliCommand::Hottest(HottestConfig {
-            repository_path: repository_path(cmd_matches.value_of("SOURCE")),
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            history: history_filter(cmd_matches)?,
+            recurse_submodules: cmd_matches.is_present("RECURSE_SUBMODULES"),
+            top: parse_number(cmd_matches, "TOP")?,
+            changes: changes(cmd_matches),
+            format,
+            group_by,
+            config_path: arg_matches.value_of("config").map(String::from),
+        })
+    } else if arg_matches.subcommand_matches(KNOWLEDGE_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(KNOWLEDGE_CMD).unwrap();
+        let departed: Vec<String> = cmd_matches
+            .values_of("DEPARTED")
+            .map(|v| v.map(String::from).collect())
+            .unwrap_or_default();
+        let inactive_months = if cmd_matches.is_present("INACTIVE_MONTHS") {
+            Some(parse_number(cmd_matches, "INACTIVE_MONTHS")?)
+        } else {
+            None
+        };
+        if departed.is_empty() && inactive_months.is_none() {
+            return Err(HotspotError::InvalidConfig(
+                "say who has left with --departed or --inactive-months".to_string(),
+            ));
+        }
+        CliCommand::Knowledge(KnowledgeConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
             verbosity: verbosity(&arg_matches),
             output: "".to_string(),
             includes: "".to_string(),
             excludes: "".to_string(),
-            top: cmd_matches.value_of("TOP").unwrap().parse().unwrap(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            revision: cmd_matches.value_of("REV").unwrap_or("HEAD").to_string(),
+            departed,
+            inactive_months,
+            group_by: grouping(cmd_matches)?,
+            config_path: arg_matches.value_of("config").map(String::from),
+            format: output_format(&arg_matches, cmd_matches)?,
         })
     } else if arg_matches.subcommand_matches(METRICS_CMD).is_some() {
         let cmd_matches = arg_matches.subcommand_matches(METRICS_CMD).unwrap();
+        let format = output_format(&arg_matches, cmd_matches)?;
+        let group_by = grouping(cmd_matches)?;
+        if group_by.is_some() && matches!(format, OutputFormat::Sarif | OutputFormat::Svg) {
+            return Err(HotspotError::InvalidConfig(
+                "--group-by only works with table and markdown output".to_string(),
+            ));
+        }
         CliCommand::Metrics(MetricsConfig {
-            repository_path: repository_path(cmd_matches.value_of("SOURCE")),
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            revision: cmd_matches.value_of("REV").map(String::from),
+            format,
+            changes: changes(cmd_matches),
+            group_by,
+            config_path: arg_matches.value_of("config").map(String::from),
+            top: parse_number(cmd_matches, "TOP")?,
+        })
+    } else if arg_matches.subcommand_matches(REPORT_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(REPORT_CMD).unwrap();
+        CliCommand::Report(ReportConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
+            verbosity: verbosity(&arg_matches),
+            output: "".to_string(),
+            includes: "".to_string(),
+            excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            revision: cmd_matches.value_of("REV").unwrap_or("HEAD").to_string(),
+            history: history_filter(cmd_matches)?,
+            html: cmd_matches.value_of("HTML").unwrap_or_default().to_string(),
+        })
+    } else if arg_matches.subcommand_matches(TREND_CMD).is_some() {
+        let cmd_matches = arg_matches.subcommand_matches(TREND_CMD).unwrap();
+        let interval = if cmd_matches.is_present("EVERY") {
+            let every = parse_number(cmd_matches, "EVERY")?;
+            if every == 0 {
+                return Err(HotspotError::InvalidConfig(
+                    "--every needs to be at least 1".to_string(),
+                ));
+            }
+            Interval::Commits(every)
+        } else if cmd_matches.is_present("TAGS") {
+            Interval::Tags
+        } else {
+            Interval::Weekly
+        };
+        CliCommand::Trend(TrendConfig {
+            repository_path: repository_path(cmd_matches.value_of("SOURCE"))?,
             verbosity: verbosity(&arg_matches),
             output: "".to_string(),
             includes: "".to_string(),
             excludes: "".to_string(),
+            use_cache: !arg_matches.is_present("no-cache"),
+            jobs: parse_number(cmd_matches, "JOBS")?,
+            interval,
+            per_file: cmd_matches.is_present("PER_FILE"),
+            format: output_format(&arg_matches, cmd_matches)?,
         })
     }
     // else if arg_matches.subcommand_matches(CONTRIBUTOR_CMD).is_some() {
@@ -232,5 +971,6 @@ pub fn parse(arg_matches: ArgMatches) -> CliCommand {
     // }
     else {
         CliCommand::Nothing
-    }
+    };
+    Ok(command)
 }
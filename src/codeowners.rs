@@ -0,0 +1,289 @@
+//! The owners a `CODEOWNERS` file declares for each path, and how they compare to the people who
+//! actually change the files.
+//!
+//! Patterns follow GitHub's rules: the last pattern matching a file sets its owners, a pattern
+//! without a slash matches at any depth, one ending in a slash matches everything in a directory
+//! and `dir/*` only matches the files directly in it.
+
+use crate::error::HotspotError;
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::Repository;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Where GitHub looks for the file, in the order it looks.
+pub const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Seconds in the months owners are measured in.
+const MONTH: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    /// A GitHub user, `@name`.
+    User(String),
+    /// A GitHub team, `@org/name`, whose members are not known.
+    Team(String),
+    Email(String),
+}
+
+impl Owner {
+    fn parse(text: &str) -> Self {
+        match text.strip_prefix('@') {
+            Some(name) if name.contains('/') => Owner::Team(name.to_string()),
+            Some(name) => Owner::User(name.to_string()),
+            None => Owner::Email(text.to_string()),
+        }
+    }
+
+    /// True when `contributor` is this owner. Users are matched on the local part of the commit
+    /// email, including GitHub's `id+name@users.noreply.github.com` addresses, or on the author name.
+    pub fn is(&self, contributor: &ContributorKey) -> bool {
+        match self {
+            Owner::User(name) => {
+                let local = contributor.email().split('@').next().unwrap_or_default();
+                let local = local.split_once('+').map_or(local, |(_, name)| name);
+                local.eq_ignore_ascii_case(name) || contributor.name().eq_ignore_ascii_case(name)
+            }
+            Owner::Team(_) => false,
+            Owner::Email(email) => contributor.email().eq_ignore_ascii_case(email),
+        }
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Owner::User(name) | Owner::Team(name) => write!(f, "@{}", name),
+            Owner::Email(email) => write!(f, "{}", email),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeOwners {
+    patterns: GlobSet,
+    /// The owners of each pattern, in the order of the file. Empty for a pattern that unsets them.
+    owners: Vec<Vec<Owner>>,
+}
+
+impl CodeOwners {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        let mut owners = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let pattern = words.next().unwrap_or_default();
+            for glob in globs(pattern) {
+                let glob = GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| {
+                        format!("line {}: invalid pattern '{}': {}", number + 1, pattern, e)
+                    })?;
+                builder.add(glob);
+                owners.push(
+                    words
+                        .clone()
+                        .take_while(|w| !w.starts_with('#'))
+                        .map(Owner::parse)
+                        .collect(),
+                );
+            }
+        }
+        let patterns = builder.build().map_err(|e| e.to_string())?;
+        Ok(CodeOwners { patterns, owners })
+    }
+
+    /// Reads the first `CODEOWNERS` file found in the working directory of `repo`, along with where
+    /// it was found.
+    pub fn find(repo: &Repository) -> Result<Option<(PathBuf, Self)>, HotspotError> {
+        let dir = match repo.workdir() {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+        let path = match LOCATIONS.iter().map(|l| dir.join(l)).find(|p| p.is_file()) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let text = fs::read_to_string(&path).map_err(|e| HotspotError::io(&path, e))?;
+        let owners = CodeOwners::parse(&text)
+            .map_err(|e| HotspotError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        Ok(Some((path, owners)))
+    }
+
+    /// The owners of `path`, relative to the repository root. Empty when no pattern gives it any.
+    pub fn owners_of(&self, path: &RawPath) -> &[Owner] {
+        self.patterns
+            .matches(path.to_string_lossy())
+            .into_iter()
+            .max()
+            .map_or(&[], |i| self.owners[i].as_slice())
+    }
+}
+
+/// The globs matching what `pattern` does, relative to the repository root.
+fn globs(pattern: &str) -> Vec<String> {
+    let relative = pattern.trim_start_matches('/');
+    let directory = relative.ends_with('/');
+    let relative = relative.trim_end_matches('/');
+    let anchored = pattern.starts_with('/') || relative.contains('/');
+    let glob = if anchored {
+        relative.to_string()
+    } else {
+        format!("**/{}", relative)
+    };
+    if directory {
+        vec![format!("{}/**", glob)]
+    } else if glob.ends_with("/*") {
+        vec![glob]
+    } else {
+        // A name also matches everything in a directory of that name.
+        vec![format!("{}/**", glob), glob]
+    }
+}
+
+/// How a file's ownership has drifted from who changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drift {
+    /// None of the owners has changed the file recently, or at all.
+    InactiveOwners,
+    /// Changed often and nobody owns it.
+    Unowned,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Drift::InactiveOwners => write!(f, "owners inactive"),
+            Drift::Unowned => write!(f, "no owner"),
+        }
+    }
+}
+
+/// When a file's ownership counts as drifted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriftLimits {
+    /// Owners who have not changed a file for this many months are inactive.
+    pub inactive_months: u32,
+    /// Files changed at least this many times need an owner.
+    pub churn: u32,
+}
+
+impl DriftLimits {
+    /// How the ownership of a file changed `touches` times has drifted, given when each person last
+    /// changed it. Files owned by a team are never reported inactive as its members are not known.
+    pub fn drift(
+        &self,
+        owners: &[Owner],
+        last_changes: &HashMap<ContributorKey, i64>,
+        touches: u32,
+        now: i64,
+    ) -> Option<Drift> {
+        if owners.is_empty() {
+            return if touches >= self.churn {
+                Some(Drift::Unowned)
+            } else {
+                None
+            };
+        }
+        if owners.iter().any(|o| matches!(o, Owner::Team(_))) {
+            return None;
+        }
+        let since = now - self.inactive_months as i64 * MONTH;
+        let active = last_changes
+            .iter()
+            .any(|(c, at)| *at >= since && owners.iter().any(|o| o.is(c)));
+        if active {
+            None
+        } else {
+            Some(Drift::InactiveOwners)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codeowners::{CodeOwners, Drift, DriftLimits, Owner, MONTH};
+    use crate::shared_types::{ContributorKey, RawPath};
+    use std::collections::HashMap;
+
+    fn owners_of(owners: &CodeOwners, path: &str) -> Vec<String> {
+        owners
+            .owners_of(&RawPath::from(path))
+            .iter()
+            .map(|o| o.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn the_last_matching_pattern_sets_the_owners() {
+        let owners = CodeOwners::parse(
+            "# Everyone\n\
+             *       @core\n\
+             *.js    @web frontend@example.com # comment\n\
+             /docs/  @org/writers\n\
+             apps/*  @apps\n\
+             /vendor/\n",
+        )
+        .unwrap();
+        assert_eq!(owners_of(&owners, "src/main.rs"), vec!["@core"]);
+        assert_eq!(
+            owners_of(&owners, "src/ui/app.js"),
+            vec!["@web", "frontend@example.com"]
+        );
+        assert_eq!(
+            owners_of(&owners, "docs/guide/index.md"),
+            vec!["@org/writers"]
+        );
+        assert_eq!(owners_of(&owners, "apps/a.rs"), vec!["@apps"]);
+        // `apps/*` only owns the files directly in `apps`.
+        assert_eq!(owners_of(&owners, "apps/web/b.rs"), vec!["@core"]);
+        assert!(owners_of(&owners, "vendor/lib.rs").is_empty());
+    }
+
+    #[test]
+    fn users_match_commit_emails_and_names() {
+        let noreply = ContributorKey::new(
+            "123+octocat@users.noreply.github.com".to_string(),
+            "The Octocat".to_string(),
+        );
+        assert!(Owner::User("octocat".to_string()).is(&noreply));
+        let named = ContributorKey::new("o@example.com".to_string(), "octocat".to_string());
+        assert!(Owner::User("OctoCat".to_string()).is(&named));
+        assert!(!Owner::Email("other@example.com".to_string()).is(&named));
+    }
+
+    #[test]
+    fn drift_flags_inactive_owners_and_busy_unowned_files() {
+        let limits = DriftLimits {
+            inactive_months: 6,
+            churn: 10,
+        };
+        let alice = ContributorKey::new("alice@example.com".to_string(), "Alice".to_string());
+        let owners = vec![Owner::User("alice".to_string())];
+        let now = 12 * MONTH;
+        let changed_at = |at: i64| HashMap::from([(alice.clone(), at)]);
+        assert_eq!(
+            limits.drift(&owners, &changed_at(now - MONTH), 1, now),
+            None
+        );
+        assert_eq!(
+            limits.drift(&owners, &changed_at(now - 7 * MONTH), 1, now),
+            Some(Drift::InactiveOwners)
+        );
+        let team = vec![Owner::Team("org/core".to_string())];
+        assert_eq!(limits.drift(&team, &HashMap::new(), 1, now), None);
+        assert_eq!(limits.drift(&[], &HashMap::new(), 9, now), None);
+        assert_eq!(
+            limits.drift(&[], &HashMap::new(), 10, now),
+            Some(Drift::Unowned)
+        );
+    }
+}
@@ -1,5 +1,41 @@
 pub mod about_cmd;
 pub mod busfactor_cmd;
+pub mod cache_cmd;
+pub mod check_cmd;
+pub mod components_cmd;
 pub mod contributors_cmd;
+pub mod diff_cmd;
 pub mod hottest_cmd;
+pub mod knowledge_cmd;
 pub mod metrics_cmd;
+pub mod report_cmd;
+pub mod trend_cmd;
+
+use hotspot::cache::Save;
+use hotspot::error::HotspotError;
+use hotspot::hotspots::Hotspot;
+
+/// Writes what was added to `cache`. A cache that cannot be written is only reported as the results
+/// are still good.
+pub fn save_cache<C: Save>(cache: Option<C>) {
+    if let Some(Err(e)) = cache.map(|c| c.save()) {
+        eprintln!("Could not update the cache: {}", e);
+    }
+}
+
+/// The pool files are parsed on, with `jobs` threads or one per CPU for `0`.
+pub fn thread_pool(jobs: usize) -> Result<rayon::ThreadPool, HotspotError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|e| HotspotError::InvalidConfig(format!("could not start {} jobs: {}", jobs, e)))
+}
+
+/// The `top` highest scoring of `hotspots`, leaving out files nobody changed or without complexity.
+pub fn top_hotspots(hotspots: &[Hotspot], top: u32) -> Vec<&Hotspot> {
+    hotspots
+        .iter()
+        .filter(|h| h.score > 0)
+        .take(top as usize)
+        .collect()
+}
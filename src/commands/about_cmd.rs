@@ -2,7 +2,8 @@ pub fn execute() {
     let text = "The 'hotspot' CLI contains various tools for inspecting source code to try find those areas that warrant a closer look.
 It uses code metrics and combines that with source control history to highlight issues not just due to metrics but those metrics in light of how often and how frequent code changes.
 It also considers contributor diversity and warns on bus factor for complex and/or high change code.
-Supports: C++, C#, Go, Java, JavaScript, Python, Rust, TypeScript";
+Supports: C++, C#, F#, Go, Java, JavaScript, Python, Rust, TypeScript
+C#, F# and Go (and the complexity figures for Java) use a line based heuristic; the 'Analyzer' column of 'metrics' says which one produced each file's numbers.";
 
     println!("{}", text);
 }
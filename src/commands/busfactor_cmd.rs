@@ -1,98 +1,302 @@
-use git2::Repository;
+use crate::commands::save_cache;
+use hotspot::cache::{cache_dir, CommitCache};
+use hotspot::codeowners::{self, CodeOwners, Drift, DriftLimits};
+use hotspot::components::Components;
+use hotspot::error::HotspotError;
+use hotspot::rollup::{ChangeTotal, Grouping, Rollup};
 use hotspot::shared_types::{
-    is_supported_file, truncate_left, BusFactorConfig, ContributorKey, FILE_GLOBS,
+    supported_files, BusFactorConfig, ContributorKey, OutputFormat, RawPath,
 };
+use hotspot::table::{self, Cell, Highlight, Table};
+use hotspot::teams::Teams;
+use hotspot::{changes, history};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use term_table::TableStyle;
 
-pub fn execute(config: BusFactorConfig) {
-    if config.verbosity.is_not_quiet() {
+extern crate chrono;
+use chrono::prelude::*;
+
+/// How often a file was changed, and when each person who changed it last did.
+#[derive(Default)]
+struct FileChanges {
+    touches: u32,
+    last_changes: HashMap<ContributorKey, i64>,
+}
+
+pub fn execute(config: BusFactorConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
         println!("Executing busfactor command...");
         println!("Verbosity: {}", config.verbosity);
         println!("Path: {}", config.repository_path);
     }
 
     let base_dir = Path::new(&config.repository_path);
-    let repo = match Repository::open(base_dir) {
-        Ok(repo) => repo,
-        Err(e) => panic!("failed to open: {}", e),
-    };
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
 
-    let mut file_contributors: HashMap<String, HashSet<ContributorKey>> = HashMap::new();
-    let mut rev_walk = repo.revwalk().unwrap();
-    rev_walk.push_head().unwrap();
-    let mut i = 0;
-    for elem in rev_walk {
-        let oid = elem.unwrap();
-        let commit = repo.find_commit(oid).unwrap();
+    let commit_cache = if config.use_cache {
+        CommitCache::open(&cache_dir(&repo)).ok()
+    } else {
+        None
+    };
+    let submodules = if config.recurse_submodules {
+        history::open_submodules(&repo)?
+    } else {
+        vec![]
+    };
+    let supported = supported_files();
+    let (mut file_contributors, commit_count) = history::walk(&repo, &config.history)?
+        .with_submodules(&submodules)?
+        .with_cache(commit_cache.as_ref())
+        .within(scope.clone())
+        .try_fold(
+            (HashMap::new(), 0),
+            |(mut file_contributors, commit_count), record| -> Result<_, HotspotError> {
+                let record = record?;
+                for delta in record.deltas {
+                    if supported.is_match(delta.path.to_string_lossy()) {
+                        let file: &mut FileChanges =
+                            file_contributors.entry(delta.path).or_default();
+                        file.touches += 1;
+                        let at = file
+                            .last_changes
+                            .entry(record.author.clone())
+                            .or_insert(record.time);
+                        *at = (*at).max(record.time);
+                    }
+                }
+                Ok((file_contributors, commit_count + 1))
+            },
+        )?;
 
-        let author = commit.author();
-        let name = author.name().unwrap();
-        let email = author.email().unwrap();
-        let parent_count = commit.parent_count();
-        if parent_count == 0 || parent_count == 1 {
-            let tree = commit.tree().unwrap();
-            let diff = if parent_count == 0 {
-                let prev_tree = None;
-                let diff = repo
-                    .diff_tree_to_tree(prev_tree, Some(&tree), None)
-                    .unwrap();
-                diff
-            } else if parent_count == 1 {
-                let prev_commit = commit.parent(0).unwrap();
-                let prev_tree = prev_commit.tree().unwrap();
-                let diff = repo
-                    .diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)
-                    .unwrap();
-                diff
-            } else {
-                panic!("`parent_count` unexpectedly {}", parent_count);
-            };
+    // The whole history is still counted, only the report is limited to the changed files.
+    if let Some(changes) = &config.changes {
+        let changed = changes::changed_files(&repo, changes)?;
+        file_contributors.retain(|path, _| changed.contains(path));
+    }
 
-            for delta in diff.deltas() {
-                let file_path = delta.new_file().path().unwrap();
-                //let file_mod_time = commit.time();
-                //let unix_time = file_mod_time.seconds();
-                let path_str = file_path.to_str().unwrap();
-                let key = path_str.to_string();
+    save_cache(commit_cache);
 
-                if is_supported_file(FILE_GLOBS.to_vec(), path_str) {
-                    let h = file_contributors
-                        .entry(key.clone())
-                        .or_insert(HashSet::new());
-                    h.insert(ContributorKey::new(email.to_string(), name.to_string()));
-                }
+    match (config.group_by, config.owners) {
+        (_, Some(limits)) => {
+            let (path, owners) = CodeOwners::find(&repo)?.ok_or_else(|| {
+                HotspotError::InvalidConfig(format!(
+                    "no CODEOWNERS file found, looked for {}",
+                    codeowners::LOCATIONS.join(", ")
+                ))
+            })?;
+            if config.verbosity.is_not_quiet() && config.format.is_table() {
+                println!("Owners: {}", path.display());
+            }
+            output_owners(config.format, &file_contributors, &owners, limits);
+        }
+        (Some(grouping), None) => {
+            let mut rollup = match grouping {
+                Grouping::Component => Rollup::by_component(Components::load(
+                    config.config_path.as_deref().map(Path::new),
+                    &repo,
+                )?),
+                _ => Rollup::new(grouping, scope.as_ref()),
+            };
+            for (path, file) in &file_contributors {
+                rollup.add(path, |total: &mut ChangeTotal| {
+                    total.add(0, file.last_changes.keys(), 0)
+                });
             }
+            output_groups(config, &rollup);
         }
+        (None, None) => {
+            let teams = if config.by_team {
+                Some(Teams::load(
+                    config.config_path.as_deref().map(Path::new),
+                    &repo,
+                )?)
+            } else {
+                None
+            };
+            output(config, file_contributors, teams.as_ref(), commit_count)
+        }
+    }
+    Ok(())
+}
 
-        i = i + 1;
+/// Only one or two people know these files, or no team does.
+fn risk(bus_factor: usize) -> Highlight {
+    match bus_factor {
+        0 | 1 => Highlight::Danger,
+        2 => Highlight::Warning,
+        _ => Highlight::None,
     }
+}
 
-    output(config, file_contributors, i);
+fn output_groups(config: BusFactorConfig, rollup: &Rollup<ChangeTotal>) {
+    let mut groups: Vec<(String, &ChangeTotal)> = rollup.groups().collect();
+    // A tree stays in path order so directories come before what is in them.
+    if !config.group_by.is_some_and(|g| g.is_tree()) {
+        groups.sort_by(|(a, x), (b, y)| x.bus_factor().cmp(&y.bus_factor()).then_with(|| a.cmp(b)));
+    }
+    if let OutputFormat::Markdown { .. } = config.format {
+        let single = groups.iter().filter(|(_, t)| t.bus_factor() == 1).count();
+        table::print_line(
+            config.format,
+            &format!("{} groups, {} with a bus factor of 1", rollup.len(), single),
+        );
+    }
+    let mut table = Table::new()
+        .path(match config.group_by {
+            Some(Grouping::Component) => "Component",
+            _ => "Directory",
+        })
+        .right("Files")
+        .right("Bus factor")
+        .right("Files with bus factor 1");
+    for (name, total) in groups {
+        table.add_row(vec![
+            Cell::from(name),
+            total.files.to_string().into(),
+            Cell::new(total.bus_factor(), risk(total.bus_factor())),
+            total.single_contributor_files.to_string().into(),
+        ]);
+    }
+    println!("{}", table.render(config.format));
 }
 
 fn output(
-    _config: BusFactorConfig,
-    file_contributors: HashMap<String, HashSet<ContributorKey>>,
+    config: BusFactorConfig,
+    file_contributors: HashMap<RawPath, FileChanges>,
+    teams: Option<&Teams>,
     _commit_count: i32,
 ) {
-    let mut table = term_table::Table::new();
-    table.max_column_width = 400;
-    table.style = TableStyle::thin();
-    table.add_row(term_table::row::Row::new(vec![
-        term_table::table_cell::TableCell::new("Path"),
-        term_table::table_cell::TableCell::new("Bus factor"),
-    ]));
-    for (p, cs) in file_contributors {
-        table.add_row(term_table::row::Row::new(vec![
-            term_table::table_cell::TableCell::new(truncate_left(p, 70)),
-            term_table::table_cell::TableCell::new_with_alignment(
-                cs.len(),
-                1,
-                term_table::table_cell::Alignment::Right,
+    // With teams the bus factor is how many teams have changed the file. People no team lists are
+    // not a team, they are counted on their own.
+    let mut files: Vec<(RawPath, usize, usize)> = file_contributors
+        .into_iter()
+        .map(|(p, file)| match teams {
+            Some(teams) => {
+                let mut names = HashSet::new();
+                let mut without_team = 0;
+                for contributor in file.last_changes.keys() {
+                    match teams.team_of(contributor) {
+                        Some(team) => {
+                            names.insert(team);
+                        }
+                        None => without_team += 1,
+                    }
+                }
+                (p, names.len(), without_team)
+            }
+            None => (p, file.last_changes.len(), 0),
+        })
+        .collect();
+    let title = if teams.is_some() {
+        "Team bus factor"
+    } else {
+        "Bus factor"
+    };
+    // Riskiest first, so the files a collapsed Markdown table shows are the ones that matter.
+    files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    if let OutputFormat::Markdown { .. } = config.format {
+        let single = files.iter().filter(|(_, n, _)| *n == 1).count();
+        table::print_line(
+            config.format,
+            &format!(
+                "{} files, {} with a {} of 1",
+                files.len(),
+                single,
+                title.to_lowercase()
+            ),
+        );
+    }
+    let mut table = Table::new().path("Path").right(title);
+    if teams.is_some() {
+        table = table.right("Without a team");
+    }
+    for (p, n, without_team) in files {
+        let mut row = vec![Cell::from(p.to_string()), Cell::new(n, risk(n))];
+        if teams.is_some() {
+            row.push(without_team.to_string().into());
+        }
+        table.add_row(row);
+    }
+    println!("{}", table.render(config.format));
+}
+
+/// Each file's declared owners next to who changes it, drifted files first.
+fn output_owners(
+    format: OutputFormat,
+    file_contributors: &HashMap<RawPath, FileChanges>,
+    owners: &CodeOwners,
+    limits: DriftLimits,
+) {
+    let now = Utc::now().timestamp();
+    let mut files: Vec<(&RawPath, &FileChanges, Option<Drift>)> = file_contributors
+        .iter()
+        .map(|(path, file)| {
+            let drift = limits.drift(
+                owners.owners_of(path),
+                &file.last_changes,
+                file.touches,
+                now,
+            );
+            (path, file, drift)
+        })
+        .collect();
+    let rank = |drift: Option<Drift>| match drift {
+        Some(Drift::InactiveOwners) => 0,
+        Some(Drift::Unowned) => 1,
+        None => 2,
+    };
+    files.sort_by(|a, b| rank(a.2).cmp(&rank(b.2)).then_with(|| a.0.cmp(b.0)));
+    if let OutputFormat::Markdown { .. } = format {
+        let count = |drift: Drift| files.iter().filter(|f| f.2 == Some(drift)).count();
+        table::print_line(
+            format,
+            &format!(
+                "{} files, {} whose owners have not changed them in {} months, {} changed {} or more times without an owner",
+                files.len(),
+                count(Drift::InactiveOwners),
+                limits.inactive_months,
+                count(Drift::Unowned),
+                limits.churn
             ),
-        ]));
+        );
+    }
+    let mut table = Table::new()
+        .path("Path")
+        .text("Owners")
+        .right("Changes")
+        .right("Contributors")
+        .right("Owners contributing")
+        .column("Last changed by an owner")
+        .column("Drift");
+    for (path, file, drift) in files {
+        let declared = owners.owners_of(path);
+        let by_owners: Vec<(&ContributorKey, &i64)> = file
+            .last_changes
+            .iter()
+            .filter(|(c, _)| declared.iter().any(|o| o.is(c)))
+            .collect();
+        let last = by_owners
+            .iter()
+            .map(|(_, at)| **at)
+            .max()
+            .and_then(|at| DateTime::from_timestamp(at, 0))
+            .map(|at| at.date_naive().to_string())
+            .unwrap_or_default();
+        let names: Vec<String> = declared.iter().map(|o| o.to_string()).collect();
+        table.add_row(vec![
+            Cell::from(path.to_string()),
+            names.join(" ").into(),
+            file.touches.to_string().into(),
+            file.last_changes.len().to_string().into(),
+            by_owners.len().to_string().into(),
+            last.into(),
+            match drift {
+                Some(drift @ Drift::InactiveOwners) => Cell::danger(drift.to_string()),
+                Some(drift @ Drift::Unowned) => Cell::warning(drift.to_string()),
+                None => Cell::from(""),
+            },
+        ]);
     }
-    println!("{}", table.render());
+    println!("{}", table.render(format));
 }
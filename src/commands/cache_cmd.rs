@@ -0,0 +1,36 @@
+use hotspot::cache::{self, cache_dir};
+use hotspot::error::HotspotError;
+use hotspot::history;
+use hotspot::shared_types::{CacheAction, CacheConfig};
+use std::path::Path;
+
+pub fn execute(config: CacheConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() {
+        println!("Executing cache command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let dir = cache_dir(&repo);
+
+    match config.action {
+        CacheAction::Clear => {
+            cache::clear(&dir).map_err(|e| HotspotError::io(&dir, e))?;
+            println!("Cleared cache at {}", dir.display());
+        }
+        CacheAction::Stats => {
+            let stats = cache::stats(&dir).map_err(|e| HotspotError::io(&dir, e))?;
+            println!("Cache location: {}", dir.display());
+            println!(
+                "Written by version: {}",
+                stats.version.unwrap_or_else(|| "-".to_string())
+            );
+            println!("Commits cached: {}", stats.commits);
+            println!("File metrics cached: {}", stats.blobs);
+            println!("Size on disk: {} bytes", stats.bytes);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use crate::commands::{save_cache, thread_pool, top_hotspots};
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::baseline::{Baseline, BaselineComparison};
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::check::{self, CheckInput};
+use hotspot::config::{ProjectConfig, Severity};
+use hotspot::error::HotspotError;
+use hotspot::history::{self, HistoryFilter};
+use hotspot::hotspots::{self, Hotspot};
+use hotspot::sarif::SarifReport;
+use hotspot::shared_types::{CheckConfig, OutputFormat};
+use hotspot::snapshot;
+use hotspot::table::{self, count, Cell, Table};
+use std::collections::HashSet;
+use std::path::Path;
+
+pub fn execute(config: CheckConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Executing check command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    let project = ProjectConfig::load(config.config_path.as_deref().map(Path::new), &repo)?;
+    let rules = if project.rules.is_empty() {
+        ProjectConfig::default_rules()
+    } else {
+        project.rules
+    };
+    let (blob_cache, commit_cache) = if config.use_cache {
+        let dir = cache_dir(&repo);
+        (BlobCache::open(&dir).ok(), CommitCache::open(&dir).ok())
+    } else {
+        (None, None)
+    };
+    let pool = thread_pool(config.jobs)?;
+
+    let registry = AnalyzerRegistry::default();
+    // Only the changed files are checked, as staged or on disk for uncommitted changes, but their
+    // history is still read in full.
+    let files = match &config.changes {
+        Some(changes) => snapshot::changed_files(&repo, &config.revision, changes, scope.as_ref())?,
+        None => snapshot::files_at(&repo, &config.revision, scope.as_ref())?,
+    };
+    let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+    let activity = Activity::collect(
+        history::walk_from(&repo, &config.revision, &HistoryFilter::default())?
+            .with_cache(commit_cache.as_ref())
+            .within(scope),
+    )?;
+    let hotspots = hotspots::rank(&metrics, &activity);
+    let violations = check::evaluate(
+        &rules,
+        &CheckInput {
+            metrics: &metrics,
+            hotspots: &hotspots,
+            activity: &activity,
+        },
+    )?;
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+    save_cache(commit_cache);
+
+    let v = config.verbosity.is_not_quiet() && config.format.is_table();
+    // When only a change is checked the baseline entries of the other files are left alone.
+    let changed: Option<HashSet<String>> = config
+        .changes
+        .as_ref()
+        .map(|_| files.iter().map(|f| f.path.to_string_lossy()).collect());
+    let checked = |path: &str| changed.as_ref().is_none_or(|c| c.contains(path));
+    if config.write_baseline {
+        let path = Path::new(config.baseline.as_deref().unwrap_or_default());
+        let baseline = match &changed {
+            Some(_) if path.exists() => {
+                let mut baseline = Baseline::load(path)?;
+                baseline.update(&violations, checked);
+                baseline
+            }
+            _ => Baseline::from_violations(&violations),
+        };
+        baseline.save(path)?;
+        // Kept off stdout, where it would end up in a SARIF document.
+        eprintln!(
+            "Recorded {} in {}",
+            count(violations.len(), "violation"),
+            path.display()
+        );
+        return Ok(());
+    }
+    let comparison = match &config.baseline {
+        Some(path) => Baseline::load(Path::new(path))?
+            .only(checked)
+            .compare(&violations),
+        None => Baseline::default().compare(&violations),
+    };
+    match config.format {
+        OutputFormat::Sarif => {
+            let top = top_hotspots(&hotspots, config.top);
+            output_sarif(&comparison, &top, repo.workdir());
+        }
+        // The check command is not offered as SVG.
+        _ => output(&comparison, config.baseline.is_some(), config.format),
+    }
+    if v {
+        println!("Check command completed in: {}s", time_taken_sec);
+    }
+    let errors = comparison
+        .regressions
+        .iter()
+        .filter(|r| r.violation.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(HotspotError::ChecksFailed(errors));
+    }
+    Ok(())
+}
+
+fn output(comparison: &BaselineComparison, with_baseline: bool, format: OutputFormat) {
+    let regressions = &comparison.regressions;
+    let errors = regressions
+        .iter()
+        .filter(|r| r.violation.severity == Severity::Error)
+        .count();
+    if regressions.is_empty() && with_baseline {
+        table::print_line(format, "No new rule violations");
+    } else if regressions.is_empty() {
+        table::print_line(format, "No rule violations");
+    } else {
+        let mut table = Table::new()
+            .column("Rule")
+            .column("Severity")
+            .path("File")
+            .text("Function")
+            .right("Line")
+            .right("Value")
+            .right("Limit");
+        if with_baseline {
+            table = table.right("Baseline");
+        }
+        for r in regressions {
+            let v = &r.violation;
+            let severity = match v.severity {
+                Severity::Error => Cell::danger(v.severity),
+                Severity::Warning => Cell::warning(v.severity),
+            };
+            let mut row = vec![
+                v.rule.to_string().into(),
+                severity,
+                v.path.to_string().into(),
+                v.function.clone().unwrap_or_default().into(),
+                v.line.map(|l| l.to_string()).unwrap_or_default().into(),
+                v.value.to_string().into(),
+                v.limit.to_string().into(),
+            ];
+            if with_baseline {
+                row.push(
+                    r.was
+                        .map(|w| w.to_string())
+                        .unwrap_or_else(|| "new".to_string())
+                        .into(),
+                );
+            }
+            table.add_row(row);
+        }
+        println!("{}", table.render(format));
+    }
+
+    if !comparison.fixed.is_empty() {
+        table::print_line(
+            format,
+            "Fixed since the baseline, rewrite it with --write-baseline to drop them:",
+        );
+        let mut table = Table::new()
+            .column("Rule")
+            .path("File")
+            .text("Function")
+            .right("Baseline");
+        for e in &comparison.fixed {
+            table.add_row(vec![
+                e.rule.to_string(),
+                e.path.clone(),
+                e.function.clone().unwrap_or_default(),
+                e.value.to_string(),
+            ]);
+        }
+        println!("{}", table.render(format));
+    }
+
+    let summary = if with_baseline {
+        format!(
+            "{} ({}, {}), {} in the baseline, {} fixed",
+            count(regressions.len(), "new or worse violation"),
+            count(errors, "error"),
+            count(regressions.len() - errors, "warning"),
+            comparison.known,
+            comparison.fixed.len()
+        )
+    } else {
+        format!(
+            "{} ({}, {})",
+            count(regressions.len(), "violation"),
+            count(errors, "error"),
+            count(regressions.len() - errors, "warning")
+        )
+    };
+    table::print_line(format, &summary);
+}
+
+fn output_sarif(comparison: &BaselineComparison, hotspots: &[&Hotspot], root: Option<&Path>) {
+    let mut report = SarifReport::new();
+    for r in &comparison.regressions {
+        report.push_violation(&r.violation);
+    }
+    for h in hotspots {
+        report.push_hotspot(h);
+    }
+    println!("{}", report.render(root));
+}
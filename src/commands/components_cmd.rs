@@ -0,0 +1,171 @@
+use crate::commands::{save_cache, thread_pool};
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::components::{ComponentHistory, Components};
+use hotspot::error::HotspotError;
+use hotspot::hotspots::{self, Hotspot};
+use hotspot::shared_types::{supported_files, ComponentsConfig, OutputFormat, SpecificMetrics};
+use hotspot::table::{self, Cell, Highlight, Table};
+use hotspot::{history, snapshot};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How many of the people who committed most to a component are named.
+const TOP_CONTRIBUTORS: usize = 3;
+
+pub fn execute(config: ComponentsConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Executing components command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    let components = Components::load(config.config_path.as_deref().map(Path::new), &repo)?;
+    let (blob_cache, commit_cache) = if config.use_cache {
+        let dir = cache_dir(&repo);
+        (BlobCache::open(&dir).ok(), CommitCache::open(&dir).ok())
+    } else {
+        (None, None)
+    };
+    let pool = thread_pool(config.jobs)?;
+
+    let registry = AnalyzerRegistry::default();
+    let files = snapshot::files_at(&repo, &config.revision, scope.as_ref())?;
+    let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+    let mut component_history = ComponentHistory::new(&components);
+    let mut activity = Activity::default();
+    let supported = supported_files();
+    for record in history::walk_from(&repo, &config.revision, &config.history)?
+        .with_cache(commit_cache.as_ref())
+        .within(scope)
+    {
+        let mut record = record?;
+        record
+            .deltas
+            .retain(|d| supported.is_match(d.path.to_string_lossy()));
+        component_history.add(&components, &record);
+        activity.add(record);
+    }
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+    save_cache(commit_cache);
+
+    let hotspots = hotspots::rank(&metrics, &activity);
+    output(
+        config.format,
+        &components,
+        &component_history,
+        &metrics,
+        &hotspots,
+    );
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Components command completed in: {}s", time_taken_sec);
+    }
+    Ok(())
+}
+
+/// Only one or two people know these components.
+fn risk(truck_factor: usize) -> Highlight {
+    match truck_factor {
+        1 => Highlight::Danger,
+        2 => Highlight::Warning,
+        _ => Highlight::None,
+    }
+}
+
+fn output(
+    format: OutputFormat,
+    components: &Components,
+    history: &ComponentHistory,
+    metrics: &[SpecificMetrics],
+    hotspots: &[Hotspot],
+) {
+    let mut files: HashMap<&str, (usize, i64)> = HashMap::new();
+    for m in metrics.iter().filter(|m| m.loc.is_some()) {
+        for name in components.of(&m.path) {
+            let (count, loc) = files.entry(name).or_default();
+            *count += 1;
+            *loc += m.loc.unwrap_or_default();
+        }
+    }
+    if let OutputFormat::Markdown { .. } = format {
+        table::print_line(
+            format,
+            &format!(
+                "{} components, {} pairs changed together",
+                history.components.len(),
+                history.co_changes.len()
+            ),
+        );
+    }
+    let mut table = Table::new()
+        .column("Component")
+        .right("Files")
+        .right("Lines")
+        .right("Commits")
+        .right("Contributors")
+        .right("Truck factor")
+        .text("Top contributors")
+        .path("Top hotspot");
+    for (name, activity) in &history.components {
+        let (count, loc) = files.get(name.as_str()).copied().unwrap_or_default();
+        let truck_factor = activity.truck_factor();
+        let top_contributors: Vec<String> = activity
+            .top_contributors(TOP_CONTRIBUTORS)
+            .into_iter()
+            .map(|(c, commits)| format!("{} ({})", c.name(), commits))
+            .collect();
+        // The highest scoring file that has been changed at all.
+        let top_hotspot = hotspots
+            .iter()
+            .filter(|h| h.score > 0)
+            .find(|h| components.of(&h.path).any(|n| n == name));
+        table.add_row(vec![
+            Cell::from(name.as_str()),
+            count.to_string().into(),
+            loc.to_string().into(),
+            activity.commits.to_string().into(),
+            activity.contributors.len().to_string().into(),
+            Cell::new(truck_factor, risk(truck_factor)),
+            top_contributors.join(", ").into(),
+            top_hotspot
+                .map(|h| h.path.to_string())
+                .unwrap_or_default()
+                .into(),
+        ]);
+    }
+    println!("{}", table.render(format));
+
+    if history.co_changes.is_empty() {
+        table::print_line(format, "Changed together: none");
+        return;
+    }
+    table::print_line(format, "Changed together:");
+    let mut pairs: Vec<(&(String, String), &u32)> = history.co_changes.iter().collect();
+    pairs.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let mut table = Table::new()
+        .column("Component")
+        .column("Component")
+        .right("Commits")
+        .right("Coupling");
+    for ((a, b), commits) in pairs {
+        // Shared commits as a share of the commits to the less often changed of the two.
+        let fewest = history.components[a]
+            .commits
+            .min(history.components[b].commits)
+            .max(1);
+        table.add_row(vec![
+            a.to_string(),
+            b.to_string(),
+            commits.to_string(),
+            format!("{}%", commits * 100 / fewest),
+        ]);
+    }
+    println!("{}", table.render(format));
+}
@@ -1,103 +1,163 @@
-use git2::Repository;
-use hotspot::shared_types::{truncate_right, ContributorKey, ContributorsConfig};
+use crate::commands::save_cache;
+use hotspot::cache::{cache_dir, CommitCache};
+use hotspot::error::HotspotError;
+use hotspot::history;
+use hotspot::shared_types::{ContributorKey, ContributorsConfig, OutputFormat, RawPath};
+use hotspot::table::{self, count, Table};
+use hotspot::teams::{TeamHistory, Teams};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use term_table::TableStyle;
 // maybe this? https://docs.rs/git2/0.13.22/git2/struct.Repository.html#method.revwalk
 // get files see code here: https://github.com/rust-lang/git2-rs/issues/588#issuecomment-856757971
 // C# impl https://github.com/libgit2/libgit2sharp/pull/963/files
 
-pub fn execute(config: ContributorsConfig) {
-    if config.verbosity.is_not_quiet() {
+/// How many of the files changed by the most teams are listed.
+const COORDINATION_HOTSPOTS: usize = 10;
+
+pub fn execute(config: ContributorsConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
         println!("Executing contributors command...");
         println!("Verbosity: {}", config.verbosity);
         println!("Path: {}", config.repository_path);
     }
     let base_dir = Path::new(&config.repository_path);
-    let repo = match Repository::open(base_dir) {
-        Ok(repo) => repo,
-        Err(e) => panic!("failed to open: {}", e),
+    let repo = history::open_repository(base_dir)?;
+    let commit_cache = if config.use_cache {
+        CommitCache::open(&cache_dir(&repo)).ok()
+    } else {
+        None
     };
-    let mut contributors: HashMap<ContributorKey, u32> = HashMap::new();
-    let mut contributor_files: HashMap<ContributorKey, HashSet<String>> = HashMap::new();
-    let mut rev_walk = repo.revwalk().unwrap();
-    rev_walk.push_head().unwrap();
-    let mut i = 0;
-    for elem in rev_walk {
-        let oid = elem.unwrap();
-        let commit = repo.find_commit(oid).unwrap();
-
-        let author = commit.author();
-        let name = author.name().unwrap();
-        let email = author.email().unwrap();
-        let key = ContributorKey::new(email.to_string(), name.to_string());
-        let parent_count = commit.parent_count();
-        if parent_count == 0 || parent_count == 1 {
-            let tree = commit.tree().unwrap();
-            let diff = if parent_count == 0 {
-                let prev_tree = None;
-                let diff = repo
-                    .diff_tree_to_tree(prev_tree, Some(&tree), None)
-                    .unwrap();
-                diff
-            } else if parent_count == 1 {
-                let prev_commit = commit.parent(0).unwrap();
-                let prev_tree = prev_commit.tree().unwrap();
-                let diff = repo
-                    .diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)
-                    .unwrap();
-                diff
-            } else {
-                panic!("`parent_count` unexpectedly {}", parent_count);
-            };
-            for delta in diff.deltas() {
-                let file_path = delta.new_file().path().unwrap();
-                //let file_mod_time = commit.time();
-                //let unix_time = file_mod_time.seconds();
-                let h = contributor_files
-                    .entry(key.clone())
-                    .or_insert(HashSet::new());
-                h.insert(file_path.to_str().unwrap().to_string());
-            }
+    let submodules = if config.recurse_submodules {
+        history::open_submodules(&repo)?
+    } else {
+        vec![]
+    };
+    if config.by_team {
+        let teams = Teams::load(config.config_path.as_deref().map(Path::new), &repo)?;
+        let mut team_history = TeamHistory::default();
+        for record in history::walk(&repo, &config.history)?
+            .with_submodules(&submodules)?
+            .with_cache(commit_cache.as_ref())
+            .within(history::scope_of(&repo, base_dir))
+        {
+            team_history.add(&teams, record?);
         }
-
-        i = i + 1;
-        *contributors.entry(key.clone()).or_insert(0) += 1;
-        let _ = *contributor_files.entry(key).or_insert(HashSet::new());
+        save_cache(commit_cache);
+        output_teams(config.format, &team_history);
+        return Ok(());
     }
+    let (contributors, contributor_files, commit_count) = history::walk(&repo, &config.history)?
+        .with_submodules(&submodules)?
+        .with_cache(commit_cache.as_ref())
+        .within(history::scope_of(&repo, base_dir))
+        .try_fold(
+            (HashMap::new(), HashMap::new(), 0),
+            |(mut contributors, mut contributor_files, commit_count),
+             record|
+             -> Result<_, HotspotError> {
+                let record = record?;
+                let h: &mut HashSet<RawPath> =
+                    contributor_files.entry(record.author.clone()).or_default();
+                for delta in record.deltas {
+                    h.insert(delta.path);
+                }
+                *contributors.entry(record.author).or_insert(0) += 1;
+                Ok((contributors, contributor_files, commit_count + 1))
+            },
+        )?;
 
-    output(config, contributors, contributor_files, i);
+    save_cache(commit_cache);
+
+    output(config, contributors, contributor_files, commit_count);
+    Ok(())
 }
 
 fn output(
-    _config: ContributorsConfig,
+    config: ContributorsConfig,
     contributors: HashMap<ContributorKey, u32>,
-    contributor_files: HashMap<ContributorKey, HashSet<String>>,
-    _commit_count: i32,
+    contributor_files: HashMap<ContributorKey, HashSet<RawPath>>,
+    commit_count: i32,
 ) {
-    let mut table = term_table::Table::new();
-    table.max_column_width = 400;
-    table.style = TableStyle::thin();
-    table.add_row(term_table::row::Row::new(vec![
-        term_table::table_cell::TableCell::new("Contributors"),
-        term_table::table_cell::TableCell::new("Commits"),
-        term_table::table_cell::TableCell::new("Files Touched"),
-    ]));
-    for k in contributors.keys() {
-        let contributor = truncate_right(k.to_string(), 70);
-        table.add_row(term_table::row::Row::new(vec![
-            term_table::table_cell::TableCell::new(contributor),
-            term_table::table_cell::TableCell::new_with_alignment(
-                contributors[k],
-                1,
-                term_table::table_cell::Alignment::Right,
-            ),
-            term_table::table_cell::TableCell::new_with_alignment(
-                contributor_files[k].len(),
-                1,
-                term_table::table_cell::Alignment::Right,
+    let mut keys: Vec<&ContributorKey> = contributors.keys().collect();
+    keys.sort_by(|a, b| {
+        contributors[*b]
+            .cmp(&contributors[*a])
+            .then_with(|| a.email().cmp(b.email()))
+    });
+    if let OutputFormat::Markdown { .. } = config.format {
+        table::print_line(
+            config.format,
+            &format!("{} contributors, {} commits", keys.len(), commit_count),
+        );
+    }
+    let mut table = Table::new()
+        .text("Contributors")
+        .right("Commits")
+        .right("Files Touched");
+    for k in keys {
+        table.add_row(vec![
+            k.to_string(),
+            contributors[k].to_string(),
+            contributor_files[k].len().to_string(),
+        ]);
+    }
+    println!("{}", table.render(config.format));
+}
+
+fn output_teams(format: OutputFormat, history: &TeamHistory) {
+    let mut teams: Vec<_> = history.teams.iter().collect();
+    teams.sort_by(|a, b| b.1.commits.cmp(&a.1.commits).then_with(|| a.0.cmp(b.0)));
+    let hotspots = history.coordination_hotspots();
+    if let OutputFormat::Markdown { .. } = format {
+        table::print_line(
+            format,
+            &format!(
+                "{}, {}, {} changed by more than one team",
+                count(history.team_count(), "team"),
+                count(
+                    teams.iter().map(|(_, t)| t.commits).sum::<u32>() as usize,
+                    "commit"
+                ),
+                count(hotspots.len(), "file")
             ),
-        ]));
+        );
+    }
+    let mut table = Table::new()
+        .text("Team")
+        .right("Members")
+        .right("Commits")
+        .right("Files Touched");
+    for (name, team) in teams {
+        table.add_row(vec![
+            name.to_string(),
+            team.members.len().to_string(),
+            team.commits.to_string(),
+            team.files.len().to_string(),
+        ]);
+    }
+    println!("{}", table.render(format));
+
+    if hotspots.is_empty() {
+        table::print_line(format, "Changed by more than one team: none");
+        return;
+    }
+    table::print_line(format, "Changed by the most teams:");
+    let mut table = Table::new()
+        .path("Path")
+        .right("Teams")
+        .right("Changes")
+        .text("Changed by")
+        .right("Without a team");
+    for (path, file) in hotspots.into_iter().take(COORDINATION_HOTSPOTS) {
+        let mut names: Vec<&str> = file.teams.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        table.add_row(vec![
+            path.to_string(),
+            file.teams.len().to_string(),
+            file.touches.to_string(),
+            names.join(", "),
+            file.without_team.len().to_string(),
+        ]);
     }
-    println!("{}", table.render());
+    println!("{}", table.render(format));
 }
@@ -0,0 +1,213 @@
+use crate::commands::{save_cache, thread_pool};
+use git2::Repository;
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::diff::{self, FileDiff};
+use hotspot::error::HotspotError;
+use hotspot::history::{self, HistoryFilter};
+use hotspot::hotspots::{self, Hotspot};
+use hotspot::shared_types::{DiffConfig, OutputFormat, RawPath, SpecificMetrics};
+use hotspot::snapshot;
+use hotspot::table::{self, Cell, Table};
+use rayon::ThreadPool;
+use std::path::Path;
+
+/// One side of the comparison.
+struct Revision {
+    metrics: Vec<SpecificMetrics>,
+    hotspots: Vec<Hotspot>,
+}
+
+pub fn execute(config: DiffConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Executing diff command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let registry = AnalyzerRegistry::default();
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    let (blob_cache, commit_cache) = if config.use_cache {
+        let dir = cache_dir(&repo);
+        (BlobCache::open(&dir).ok(), CommitCache::open(&dir).ok())
+    } else {
+        (None, None)
+    };
+    let pool = thread_pool(config.jobs)?;
+
+    let measure = |revision: &str| -> Result<Revision, HotspotError> {
+        measure_revision(
+            &repo,
+            revision,
+            scope.as_ref(),
+            &pool,
+            &registry,
+            blob_cache.as_ref(),
+            commit_cache.as_ref(),
+        )
+    };
+    let base = measure(&config.base)?;
+    let head = measure(&config.head)?;
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+    save_cache(commit_cache);
+
+    let v = config.verbosity.is_not_quiet() && config.format.is_table();
+    output(config, base, head);
+    if v {
+        println!("Diff command completed in: {}s", time_taken_sec);
+    }
+    Ok(())
+}
+
+fn measure_revision(
+    repo: &Repository,
+    revision: &str,
+    scope: Option<&RawPath>,
+    pool: &ThreadPool,
+    registry: &AnalyzerRegistry,
+    blob_cache: Option<&BlobCache>,
+    commit_cache: Option<&CommitCache>,
+) -> Result<Revision, HotspotError> {
+    let files = snapshot::files_at(repo, revision, scope)?;
+    let metrics = snapshot::measure(pool, repo, &files, registry, blob_cache)?;
+    let history = history::walk_from(repo, revision, &HistoryFilter::default())?
+        .with_cache(commit_cache)
+        .within(scope.cloned());
+    let activity = Activity::collect(history)?;
+    let hotspots = hotspots::rank(&metrics, &activity);
+    Ok(Revision { metrics, hotspots })
+}
+
+fn signed(value: i64) -> String {
+    format!("{:+}", value)
+}
+
+/// A change in complexity, highlighted when it grew.
+fn growth(delta: i64) -> Cell {
+    if delta > 0 {
+        Cell::warning(signed(delta))
+    } else {
+        Cell::from(signed(delta))
+    }
+}
+
+/// `base -> head`, leaving out the side the file or function is missing from.
+fn change(base: Option<i64>, head: Option<i64>) -> String {
+    match (base, head) {
+        (Some(b), Some(h)) => format!("{} -> {}", b, h),
+        (None, Some(h)) => h.to_string(),
+        (Some(b), None) => b.to_string(),
+        (None, None) => "-".to_string(),
+    }
+}
+
+fn output(config: DiffConfig, base: Revision, head: Revision) {
+    let format = config.format;
+    let changes = diff::hotspot_changes(&base.hotspots, &head.hotspots, config.top as usize);
+    let mut files: Vec<FileDiff> = diff::compare(base.metrics, head.metrics);
+    files.sort_by_key(|f| -f.cognitive_delta());
+
+    table::print_line(
+        format,
+        &format!("Comparing {} to {}", config.base, config.head),
+    );
+    table::print_line(
+        format,
+        &format!(
+            "Files changed: {}, net lines: {}, net cognitive: {}, net cyclomatic: {}",
+            files.len(),
+            signed(files.iter().map(FileDiff::loc_delta).sum()),
+            signed(files.iter().map(FileDiff::cognitive_delta).sum()),
+            signed(files.iter().map(FileDiff::cyclomatic_delta).sum()),
+        ),
+    );
+
+    if !files.is_empty() {
+        let mut table = Table::new()
+            .path("File")
+            .column("Change")
+            .right("Lines")
+            .right("Cognitive")
+            .right("Lines +/-")
+            .right("Cognitive +/-");
+        for f in &files {
+            table.add_row(vec![
+                Cell::from(f.path.to_string()),
+                f.status().name().into(),
+                change(
+                    f.base.as_ref().and_then(|m| m.loc),
+                    f.head.as_ref().and_then(|m| m.loc),
+                )
+                .into(),
+                change(
+                    f.base.as_ref().and_then(|m| m.cognitive),
+                    f.head.as_ref().and_then(|m| m.cognitive),
+                )
+                .into(),
+                signed(f.loc_delta()).into(),
+                growth(f.cognitive_delta()),
+            ]);
+        }
+        println!("{}", table.render(format));
+    }
+
+    let mut functions: Vec<(&RawPath, diff::FunctionDiff)> = files
+        .iter()
+        .flat_map(|f| f.functions().into_iter().map(move |func| (&f.path, func)))
+        .filter(|(_, func)| func.cognitive_delta() != 0)
+        .collect();
+    functions.sort_by_key(|(_, func)| -func.cognitive_delta());
+    if !functions.is_empty() {
+        let mut table = Table::new()
+            .path("File")
+            .text("Function")
+            .right("Line")
+            .right("Cognitive")
+            .right("Cognitive +/-");
+        for (path, func) in &functions {
+            table.add_row(vec![
+                Cell::from(path.to_string()),
+                func.name.clone().into(),
+                func.start_line().to_string().into(),
+                change(
+                    func.base.as_ref().map(|f| f.cognitive),
+                    func.head.as_ref().map(|f| f.cognitive),
+                )
+                .into(),
+                growth(func.cognitive_delta()),
+            ]);
+        }
+        println!("{}", table.render(format));
+    }
+
+    output_hotspots("New hotspots", &changes.introduced, format);
+    output_hotspots("Removed hotspots", &changes.removed, format);
+}
+
+fn output_hotspots(title: &str, hotspots: &[Hotspot], format: OutputFormat) {
+    if hotspots.is_empty() {
+        table::print_line(format, &format!("{}: none", title));
+        return;
+    }
+    table::print_line(format, &format!("{}:", title));
+    let mut table = Table::new()
+        .path("File")
+        .right("Score")
+        .right("Changes")
+        .right("Cognitive");
+    for h in hotspots {
+        table.add_row(vec![
+            h.path.to_string(),
+            h.score.to_string(),
+            h.touches.to_string(),
+            h.cognitive.to_string(),
+        ]);
+    }
+    println!("{}", table.render(format));
+}
@@ -1,138 +1,221 @@
-use git2::Repository;
+use crate::commands::save_cache;
+use hotspot::cache::{cache_dir, CommitCache};
+use hotspot::components::Components;
+use hotspot::error::HotspotError;
+use hotspot::rollup::{ChangeTotal, Grouping, Rollup};
+use hotspot::sarif::SarifReport;
 use hotspot::shared_types::{
-    is_supported_file, truncate_left, truncate_right, HottestConfig, FILE_GLOBS,
+    supported_files, ContributorKey, HottestConfig, OutputFormat, RawPath,
 };
-use std::collections::HashMap;
+use hotspot::table::{self, Table};
+use hotspot::{changes, history};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use term_table::TableStyle;
 
 extern crate chrono;
 use chrono::prelude::*;
 
 struct HottestReport {
     touches: u32,
-    path: String,
+    path: RawPath,
     // created_at: i64,
     // created_by: String,
     last_touched_at: i64,
     last_touched_by: String,
+    contributors: HashSet<ContributorKey>,
 }
 
-pub fn execute(config: HottestConfig) {
-    if config.verbosity.is_not_quiet() {
+pub fn execute(config: HottestConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
         println!("Executing hottest command...");
         println!("Verbosity: {}", config.verbosity);
         println!("Path: {}", config.repository_path);
     }
 
     let base_dir = Path::new(&config.repository_path);
-    let repo = match Repository::open(base_dir) {
-        Ok(repo) => repo,
-        Err(e) => panic!("failed to open: {}", e),
-    };
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
 
-    let mut file_touches: HashMap<String, HottestReport> = HashMap::new();
-    let mut rev_walk = repo.revwalk().unwrap();
-    rev_walk.push_head().unwrap();
-    let mut i = 0;
-    for elem in rev_walk {
-        let oid = elem.unwrap();
-        let commit = repo.find_commit(oid).unwrap();
+    let commit_cache = if config.use_cache {
+        CommitCache::open(&cache_dir(&repo)).ok()
+    } else {
+        None
+    };
+    let submodules = if config.recurse_submodules {
+        history::open_submodules(&repo)?
+    } else {
+        vec![]
+    };
+    let supported = supported_files();
+    let mut file_touches = history::walk(&repo, &config.history)?
+        .with_submodules(&submodules)?
+        .with_cache(commit_cache.as_ref())
+        .within(scope.clone())
+        .try_fold(
+            HashMap::new(),
+            |mut file_touches, record| -> Result<_, HotspotError> {
+                let record = record?;
+                let author = record.author;
+                let identifier = author.to_string();
+                let time = record.time;
+                for delta in record.deltas {
+                    if supported.is_match(delta.path.to_string_lossy()) {
+                        file_touches
+                            .entry(delta.path.clone())
+                            .and_modify(|e: &mut HottestReport| {
+                                e.touches += 1;
+                                e.contributors.insert(author.clone());
+                                if time > e.last_touched_at {
+                                    e.last_touched_by = identifier.to_string();
+                                    e.last_touched_at = time;
+                                }
+                            })
+                            .or_insert(HottestReport {
+                                touches: 1,
+                                path: delta.path,
+                                // created_by: identifier.to_string(),
+                                // created_at: unix_time,
+                                last_touched_by: identifier.to_string(),
+                                last_touched_at: time,
+                                contributors: HashSet::from([author.clone()]),
+                            });
+                    }
+                }
+                Ok(file_touches)
+            },
+        )?;
 
-        let author = commit.author();
-        let name = author.name().unwrap();
-        let email = author.email().unwrap();
-        let identifier = format!("{}<{}>", name, email);
-        let parent_count = commit.parent_count();
-        if parent_count == 0 || parent_count == 1 {
-            let tree = commit.tree().unwrap();
-            let diff = if parent_count == 0 {
-                let prev_tree = None;
-                let diff = repo
-                    .diff_tree_to_tree(prev_tree, Some(&tree), None)
-                    .unwrap();
-                diff
-            } else if parent_count == 1 {
-                let prev_commit = commit.parent(0).unwrap();
-                let prev_tree = prev_commit.tree().unwrap();
-                let diff = repo
-                    .diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)
-                    .unwrap();
-                diff
-            } else {
-                panic!("`parent_count` unexpectedly {}", parent_count);
-            };
+    // The whole history is still counted, only the report is limited to the changed files.
+    if let Some(changes) = &config.changes {
+        let changed = changes::changed_files(&repo, changes)?;
+        file_touches.retain(|path, _| changed.contains(path));
+    }
 
-            for delta in diff.deltas() {
-                let file_path = delta.new_file().path().unwrap();
-                let file_mod_time = commit.time();
-                let unix_time = file_mod_time.seconds();
-                let path_str = file_path.to_str().unwrap();
-                let path = path_str.to_string();
+    save_cache(commit_cache);
 
-                if is_supported_file(FILE_GLOBS.to_vec(), path_str) {
-                    file_touches
-                        .entry(path.clone())
-                        .and_modify(|e| {
-                            e.touches += 1;
-                            e.last_touched_by = identifier.to_string();
-                            e.last_touched_at = unix_time;
-                        })
-                        .or_insert(HottestReport {
-                            touches: 1,
-                            path,
-                            // created_by: identifier.to_string(),
-                            // created_at: unix_time,
-                            last_touched_by: identifier.to_string(),
-                            last_touched_at: unix_time,
-                        });
-                }
+    match config.group_by {
+        Some(grouping) => {
+            let mut rollup = match grouping {
+                Grouping::Component => Rollup::by_component(Components::load(
+                    config.config_path.as_deref().map(Path::new),
+                    &repo,
+                )?),
+                _ => Rollup::new(grouping, scope.as_ref()),
+            };
+            for report in file_touches.values() {
+                rollup.add(&report.path, |total: &mut ChangeTotal| {
+                    total.add(report.touches, &report.contributors, report.last_touched_at)
+                });
             }
+            output_groups(config, &rollup);
         }
-
-        i = i + 1;
+        None => output(config, file_touches, repo.workdir()),
     }
+    Ok(())
+}
 
-    output(config, file_touches);
+fn date_time(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|t| t.naive_utc().to_string())
+        .unwrap_or_default()
 }
 
-fn output(_config: HottestConfig, file_touches: HashMap<String, HottestReport>) {
-    let mut table = term_table::Table::new();
-    table.max_column_width = 400;
-    table.style = TableStyle::thin();
-    table.add_row(term_table::row::Row::new(vec![
-        term_table::table_cell::TableCell::new("Path"),
-        term_table::table_cell::TableCell::new("Changes"),
-        term_table::table_cell::TableCell::new("Last changed by"),
-        term_table::table_cell::TableCell::new("Last changed at"),
-    ]));
-    let mut file_touch_vec = file_touches
-        .iter()
-        .map(|(_path, row)| row)
-        .collect::<Vec<&HottestReport>>();
-    file_touch_vec.sort_by_key(|k| k.touches);
-    file_touch_vec.reverse(); // TODO: implement cmp on HottestReport and use that instead
-    if _config.top > 0 {
-        file_touch_vec.truncate(_config.top as usize);
+fn output_groups(config: HottestConfig, rollup: &Rollup<ChangeTotal>) {
+    let mut groups: Vec<(String, &ChangeTotal)> = rollup.groups().collect();
+    // A tree stays in path order so directories come before what is in them.
+    if !config.group_by.is_some_and(|g| g.is_tree()) {
+        groups.sort_by(|(a, x), (b, y)| y.touches.cmp(&x.touches).then_with(|| a.cmp(b)));
+        if config.top > 0 {
+            groups.truncate(config.top as usize);
+        }
     }
-    for row_data in file_touch_vec {
-        table.add_row(term_table::row::Row::new(vec![
-            term_table::table_cell::TableCell::new(truncate_left(row_data.path.to_string(), 70)),
-            term_table::table_cell::TableCell::new_with_alignment(
+    if let OutputFormat::Markdown { .. } = config.format {
+        table::print_line(
+            config.format,
+            &format!("{} groups of changed files", rollup.len()),
+        );
+    }
+    let mut table = Table::new()
+        .path(match config.group_by {
+            Some(Grouping::Component) => "Component",
+            _ => "Directory",
+        })
+        .right("Files")
+        .right("Changes")
+        .right("Contributors")
+        .column("Last changed at");
+    for (name, total) in groups {
+        table.add_row(vec![
+            name,
+            total.files.to_string(),
+            total.touches.to_string(),
+            total.contributors.len().to_string(),
+            // A component nothing has changed yet has no last change.
+            if total.files > 0 {
+                date_time(total.last_changed_at)
+            } else {
+                String::new()
+            },
+        ]);
+    }
+    println!("{}", table.render(config.format));
+}
+
+/// How many files are reported in SARIF output when `--top` does not say, as code scanning caps
+/// the results of a run.
+const SARIF_TOP: usize = 10;
+
+fn output(
+    config: HottestConfig,
+    file_touches: HashMap<RawPath, HottestReport>,
+    root: Option<&Path>,
+) {
+    let mut file_touch_vec = file_touches.values().collect::<Vec<&HottestReport>>();
+    file_touch_vec.sort_by_key(|k| (std::cmp::Reverse(k.touches), &k.path));
+    let changed_files = file_touch_vec.len();
+    let top = match (config.format, config.top) {
+        (OutputFormat::Sarif, 0) => SARIF_TOP,
+        (_, top) => top as usize,
+    };
+    if top > 0 {
+        file_touch_vec.truncate(top);
+    }
+    if let OutputFormat::Sarif = config.format {
+        let mut report = SarifReport::new();
+        for row_data in file_touch_vec {
+            report.push_changes(
+                &row_data.path,
                 row_data.touches,
-                1,
-                term_table::table_cell::Alignment::Right,
-                //
-            ),
-            term_table::table_cell::TableCell::new(truncate_right(
-                row_data.last_touched_by.to_string(),
-                70,
-            )),
-            term_table::table_cell::TableCell::new(truncate_right(
-                NaiveDateTime::from_timestamp(row_data.last_touched_at, 0).to_string(),
-                70,
-            )),
-        ]));
+                row_data.contributors.len(),
+            );
+        }
+        println!("{}", report.render(root));
+        return;
+    }
+    if let OutputFormat::Markdown { .. } = config.format {
+        let summary = if file_touch_vec.len() < changed_files {
+            format!(
+                "The {} most changed of {} files",
+                file_touch_vec.len(),
+                changed_files
+            )
+        } else {
+            format!("{} changed files", changed_files)
+        };
+        table::print_line(config.format, &summary);
+    }
+    let mut table = Table::new()
+        .path("Path")
+        .right("Changes")
+        .text("Last changed by")
+        .column("Last changed at");
+    for row_data in file_touch_vec {
+        table.add_row(vec![
+            row_data.path.to_string(),
+            row_data.touches.to_string(),
+            row_data.last_touched_by.to_string(),
+            date_time(row_data.last_touched_at),
+        ]);
     }
-    println!("{}", table.render());
+    println!("{}", table.render(config.format));
 }
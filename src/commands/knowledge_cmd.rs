@@ -0,0 +1,173 @@
+use crate::commands::{save_cache, thread_pool};
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::components::Components;
+use hotspot::error::HotspotError;
+use hotspot::history::{self, HistoryFilter};
+use hotspot::knowledge::{self, Departed, KnowledgeLoss, LossTotal};
+use hotspot::rollup::{Grouping, Rollup};
+use hotspot::shared_types::{KnowledgeConfig, OutputFormat};
+use hotspot::snapshot;
+use hotspot::table::{self, Cell, Highlight, Table};
+use std::path::Path;
+
+extern crate chrono;
+use chrono::prelude::*;
+
+pub fn execute(config: KnowledgeConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Executing knowledge command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    let mut rollup = match config.group_by {
+        Some(Grouping::Component) => Some(Rollup::by_component(Components::load(
+            config.config_path.as_deref().map(Path::new),
+            &repo,
+        )?)),
+        Some(grouping) => Some(Rollup::new(grouping, scope.as_ref())),
+        None => None,
+    };
+    let inactive_since = config
+        .inactive_months
+        .map(|months| (Utc::now() - chrono::Duration::days(30 * months as i64)).timestamp());
+    let departed = Departed::new(&config.departed, inactive_since)?;
+    let (blob_cache, commit_cache) = if config.use_cache {
+        let dir = cache_dir(&repo);
+        (BlobCache::open(&dir).ok(), CommitCache::open(&dir).ok())
+    } else {
+        (None, None)
+    };
+    let pool = thread_pool(config.jobs)?;
+
+    let registry = AnalyzerRegistry::default();
+    let files = snapshot::files_at(&repo, &config.revision, scope.as_ref())?;
+    let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+    let activity = Activity::collect(
+        history::walk_from(&repo, &config.revision, &HistoryFilter::default())?
+            .with_cache(commit_cache.as_ref())
+            .within(scope),
+    )?;
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+    save_cache(commit_cache);
+
+    let departed = departed.who(&activity);
+    let losses = knowledge::assess(&metrics, &activity, &departed);
+    let v = config.verbosity.is_not_quiet() && config.format.is_table();
+    if v {
+        println!("People who have left: {}", departed.len());
+    }
+    match rollup.as_mut() {
+        Some(rollup) => {
+            for loss in &losses {
+                rollup.add(&loss.path, |total: &mut LossTotal| total.add(loss));
+            }
+            output_groups(&config, rollup);
+        }
+        None => output(config.format, departed.len(), &losses),
+    }
+    if v {
+        println!("Knowledge command completed in: {}s", time_taken_sec);
+    }
+    Ok(())
+}
+
+/// The share of commits by people who have left, in red when it is all of them and in yellow when
+/// it is most.
+fn share(share: f64) -> Cell {
+    let highlight = if share >= 1.0 {
+        Highlight::Danger
+    } else if share > 0.5 {
+        Highlight::Warning
+    } else {
+        Highlight::None
+    };
+    Cell::new(format!("{:.0}%", share * 100.0), highlight)
+}
+
+/// The files mostly changed by people who have left, the most complex and changed first.
+fn output(format: OutputFormat, departed: usize, losses: &[KnowledgeLoss]) {
+    let at_risk: Vec<&KnowledgeLoss> = losses.iter().filter(|l| l.is_at_risk()).collect();
+    if let OutputFormat::Markdown { .. } = format {
+        table::print_line(
+            format,
+            &format!(
+                "{} of {} files were mostly changed by {} people who have left",
+                at_risk.len(),
+                losses.len(),
+                departed
+            ),
+        );
+    }
+    let mut table = Table::new()
+        .path("Path")
+        .right("Score")
+        .right("Cognitive")
+        .right("Changes")
+        .right("By people who left")
+        .text("Changed by");
+    for loss in at_risk {
+        let names: Vec<&str> = loss.departed.iter().map(|c| c.name()).collect();
+        table.add_row(vec![
+            Cell::from(loss.path.to_string()),
+            loss.score.to_string().into(),
+            loss.cognitive.to_string().into(),
+            loss.touches.to_string().into(),
+            share(loss.share()),
+            names.join(", ").into(),
+        ]);
+    }
+    println!("{}", table.render(format));
+}
+
+fn output_groups(config: &KnowledgeConfig, rollup: &Rollup<LossTotal>) {
+    let mut groups: Vec<(String, &LossTotal)> = rollup.groups().collect();
+    // A tree stays in path order so directories come before what is in them.
+    if !config.group_by.is_some_and(|g| g.is_tree()) {
+        groups.sort_by(|(a, x), (b, y)| {
+            y.cognitive_at_risk
+                .cmp(&x.cognitive_at_risk)
+                .then_with(|| a.cmp(b))
+        });
+    }
+    if let OutputFormat::Markdown { .. } = config.format {
+        let at_risk = groups.iter().filter(|(_, t)| t.at_risk > 0).count();
+        table::print_line(
+            config.format,
+            &format!(
+                "{} groups, {} with files mostly changed by people who have left",
+                rollup.len(),
+                at_risk
+            ),
+        );
+    }
+    let mut table = Table::new()
+        .path(match config.group_by {
+            Some(Grouping::Component) => "Component",
+            _ => "Directory",
+        })
+        .right("Files")
+        .right("At risk")
+        .right("Cognitive at risk")
+        .right("Changes")
+        .right("By people who left");
+    for (name, total) in groups {
+        table.add_row(vec![
+            Cell::from(name),
+            total.files.to_string().into(),
+            total.at_risk.to_string().into(),
+            total.cognitive_at_risk.to_string().into(),
+            total.touches.to_string().into(),
+            share(total.share()),
+        ]);
+    }
+    println!("{}", table.render(config.format));
+}
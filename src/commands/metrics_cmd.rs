@@ -1,182 +1,381 @@
-use hotspot::shared_types::{truncate_left, MetricsConfig, SpecificMetrics, FILE_GLOBS};
-use term_table::TableStyle;
+use crate::commands::{save_cache, thread_pool, top_hotspots};
+use git2::Repository;
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::changes::ChangeSet;
+use hotspot::check::{self, CheckInput, Violation};
+use hotspot::components::Components;
+use hotspot::config::ProjectConfig;
+use hotspot::error::HotspotError;
+use hotspot::history::HistoryFilter;
+use hotspot::hotspots::Hotspot;
+use hotspot::rollup::{Grouping, MetricsTotal, Rollup};
+use hotspot::sarif::SarifReport;
+use hotspot::shared_types::{MetricsConfig, OutputFormat, RawPath, SpecificMetrics, FILE_GLOBS};
+use hotspot::table::{self, Cell, Highlight, Table};
+use hotspot::treemap::{self, TreemapItem};
+use hotspot::{changes, history, hotspots, snapshot};
 extern crate globwalk;
 
 use self::globwalk::{DirEntry, GlobWalker, WalkError};
-use rust_code_analysis::{
-    CppParser, FuncSpace, JavascriptParser, ParserTrait, PreprocParser, PythonParser, RustParser,
-    TsxParser, TypescriptParser, LANG,
-};
+use rayon::prelude::*;
 use std::fs;
 use std::iter::FilterMap;
 use std::path::{Path, PathBuf};
 
-pub fn execute(config: MetricsConfig) {
-    if config.verbosity.is_not_quiet() {
+pub fn execute(config: MetricsConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
         println!("Executing metrics command...");
         println!("Verbosity: {}", config.verbosity);
         println!("Path: {}", config.repository_path);
+        if let Some(revision) = &config.revision {
+            println!("Revision: {}", revision);
+        }
     }
 
-    let mut files_scanned = 0;
-    let mut files_walked = 0;
-
     let timer = eggtimer::Timer::start();
 
+    let registry = AnalyzerRegistry::default();
     let base_dir = Path::new(&config.repository_path);
-    let walker = setup_file_walker(base_dir);
-    let metrics = walker.filter_map(|de| {
-        let dir_entry: DirEntry = de;
-        let path = dir_entry.path();
-        let m = get_metrics(path);
-        if m.is_some() {
-            files_scanned += 1;
+    if let Err(e) = fs::metadata(base_dir) {
+        return Err(HotspotError::io(base_dir, e));
+    }
+    let repo = Repository::discover(base_dir).ok();
+    // Outside of a git repository there is nowhere to keep the cache.
+    let blob_cache = if config.use_cache {
+        repo.as_ref()
+            .and_then(|repo| BlobCache::open(&cache_dir(repo)).ok())
+    } else {
+        None
+    };
+    // A bare repository has no files on disk so the ones committed at HEAD are measured instead.
+    // Staged contents are only in the index, so they are read from the repository the same way.
+    let revision = match (&config.revision, &repo, &config.changes) {
+        (Some(revision), _, _) => Some(revision.as_str()),
+        (None, Some(repo), _) if repo.is_bare() => Some("HEAD"),
+        (None, Some(_), Some(ChangeSet::Staged)) => Some("HEAD"),
+        _ => None,
+    };
+    let pool = thread_pool(config.jobs)?;
+    // Sorting up front keeps the output deterministic as the metrics are in the order of the files.
+    let (metrics, files_walked) = match revision {
+        Some(revision) => {
+            let repo = history::open_repository(base_dir)?;
+            let scope = history::scope_of(&repo, base_dir);
+            let mut files = match &config.changes {
+                Some(changes) => snapshot::changed_files(&repo, revision, changes, scope.as_ref())?,
+                None => snapshot::files_at(&repo, revision, scope.as_ref())?,
+            };
+            files.sort_by_key(|f| f.path.to_path_buf());
+            let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+            (metrics, files.len())
+        }
+        None => {
+            let mut files: Vec<PathBuf> = setup_file_walker(base_dir)?
+                .map(DirEntry::into_path)
+                .collect();
+            if let Some(changes) = &config.changes {
+                let repo = history::open_repository(base_dir)?;
+                let changed = changes::changed_files(&repo, changes)?;
+                let scope = history::scope_of(&repo, base_dir);
+                files.retain(|f| changed.contains(&repo_path(f, base_dir, None, scope.as_ref())));
+            }
+            files.sort();
+            let metrics: Vec<SpecificMetrics> = pool.install(|| {
+                files
+                    .par_iter()
+                    .filter_map(|path| get_metrics(&registry, blob_cache.as_ref(), path))
+                    .collect()
+            });
+            (metrics, files.len())
         }
-        files_walked += 1;
-        m
-    });
+    };
+    let files_scanned = metrics.len();
     let time_taken_sec = timer.elapsed();
-    let metrics_iter = metrics.into_iter();
+    save_cache(blob_cache);
 
-    let v = config.verbosity.is_not_quiet();
-    output(config, metrics_iter);
+    let v = config.verbosity.is_not_quiet() && config.format.is_table();
+    // Files read from a revision are relative to the repository root, those on disk are not.
+    let root = match revision {
+        Some(_) => repo
+            .as_ref()
+            .and_then(|r| r.workdir())
+            .map(Path::to_path_buf),
+        None => Some(base_dir.to_path_buf()),
+    };
+    match (config.format, config.group_by) {
+        (OutputFormat::Table(_) | OutputFormat::Markdown { .. }, Some(grouping)) => {
+            // Components are matched against paths from the repository root, and directories are
+            // grouped under the one being reported on.
+            let scope = repo.as_ref().and_then(|r| history::scope_of(r, base_dir));
+            let mut rollup = match (grouping, &repo) {
+                (Grouping::Component, Some(repo)) => Rollup::by_component(Components::load(
+                    config.config_path.as_deref().map(Path::new),
+                    repo,
+                )?),
+                (Grouping::Component, None) => {
+                    return Err(HotspotError::InvalidConfig(
+                        "--group-by component needs a git repository".to_string(),
+                    ))
+                }
+                _ => Rollup::new(grouping, scope.as_ref()),
+            };
+            let analyzed = metrics.iter().filter(|m| m.loc.is_some()).count();
+            for m in &metrics {
+                let path = repo_path(&m.path.to_path_buf(), base_dir, revision, scope.as_ref());
+                rollup.add(&path, |total: &mut MetricsTotal| total.add(m));
+            }
+            output_groups(config.format, &rollup, grouping, analyzed)
+        }
+        (OutputFormat::Table(_), _) => output(config, metrics),
+        (OutputFormat::Markdown { .. }, _) => {
+            // Paths from the root of the repository read best in a PR comment or wiki.
+            let workdir = repo.as_ref().and_then(|r| r.workdir());
+            output_markdown(config, metrics, workdir.or(root.as_deref()))
+        }
+        (OutputFormat::Sarif, _) => {
+            // Code scanning caps the results of a run, so only the files and functions that break
+            // a rule are reported along with the top hotspots.
+            let repo = history::open_repository(base_dir)?;
+            let scope = history::scope_of(&repo, base_dir);
+            let project = ProjectConfig::load(config.config_path.as_deref().map(Path::new), &repo)?;
+            let rules = if project.rules.is_empty() {
+                ProjectConfig::default_rules()
+            } else {
+                project.rules
+            };
+            let activity = file_history(&repo, revision, scope.as_ref(), config.use_cache)?;
+            let metrics: Vec<SpecificMetrics> = metrics
+                .into_iter()
+                .map(|mut m| {
+                    m.path = repo_path(&m.path.to_path_buf(), base_dir, revision, scope.as_ref());
+                    m
+                })
+                .collect();
+            let hotspots = hotspots::rank(&metrics, &activity);
+            let violations = check::evaluate(
+                &rules,
+                &CheckInput {
+                    metrics: &metrics,
+                    hotspots: &hotspots,
+                    activity: &activity,
+                },
+            )?;
+            output_sarif(
+                &violations,
+                &top_hotspots(&hotspots, config.top),
+                repo.workdir(),
+            );
+        }
+        (OutputFormat::Svg, _) => {
+            let repo = history::open_repository(base_dir)?;
+            let scope = history::scope_of(&repo, base_dir);
+            let activity = file_history(&repo, revision, scope.as_ref(), config.use_cache)?;
+            let items: Vec<TreemapItem> = metrics
+                .into_iter()
+                .filter_map(|m| {
+                    let path = repo_path(&m.path.to_path_buf(), base_dir, revision, scope.as_ref());
+                    Some(TreemapItem {
+                        touches: activity.files.get(&path).map_or(0, |f| f.touches),
+                        path,
+                        loc: m.loc?,
+                        cognitive: m.cognitive.unwrap_or(0),
+                    })
+                })
+                .collect();
+            print!("{}", treemap::render_svg(&items, 1200.0, 800.0));
+        }
+    }
     if v {
         println!("Files scanned for metrics: {}", files_scanned);
         println!("Total files matched: {}", files_walked);
         println!("Metrics command completed in: {}s", time_taken_sec);
     }
+    Ok(())
 }
 
-fn get_metrics(path: &Path) -> Option<SpecificMetrics> {
-    let contents_opt = fs::read(path);
-    if contents_opt.is_ok() {
-        let contents = contents_opt.unwrap();
-        let path_buf = path.to_path_buf();
-        let metrics = get_function_space(contents, &path_buf);
-        let p = path.to_str().map(|s| String::from(s)).unwrap();
-        let m = match metrics {
-            Some(function_space) => SpecificMetrics {
-                path: p,
-                loc: Some(function_space.metrics.loc.lloc() as i64),
-                cognitive: Some(function_space.metrics.cognitive.cognitive() as i64),
-                cyclomatic: Some(function_space.metrics.cyclomatic.cyclomatic() as i64),
-            },
-            None => SpecificMetrics {
-                path: p,
-                loc: None,
-                cognitive: None,
-                cyclomatic: None,
-            },
-        };
-        Some(m)
+/// Who changed each file in `scope` in the commits up to `revision`, or `HEAD`.
+fn file_history(
+    repo: &Repository,
+    revision: Option<&str>,
+    scope: Option<&RawPath>,
+    use_cache: bool,
+) -> Result<Activity, HotspotError> {
+    let commit_cache = if use_cache {
+        CommitCache::open(&cache_dir(repo)).ok()
     } else {
         None
-    }
+    };
+    let activity = Activity::collect(
+        history::walk_from(repo, revision.unwrap_or("HEAD"), &HistoryFilter::default())?
+            .with_cache(commit_cache.as_ref())
+            .within(scope.cloned()),
+    )?;
+    save_cache(commit_cache);
+    Ok(activity)
 }
 
-fn get_function_space(contents: Vec<u8>, path_buf: &PathBuf) -> Option<FuncSpace> {
-    let ext = path_buf.extension().unwrap().to_str().unwrap();
-    let lang = rust_code_analysis::get_from_ext(ext);
-    match lang {
-        Some(LANG::Rust) => {
-            let parser = RustParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Javascript) | Some(LANG::Mozjs) => {
-            let parser = JavascriptParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Typescript) => {
-            let parser = TypescriptParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Tsx) => {
-            let parser = TsxParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Python) => {
-            let parser = PythonParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Cpp) => {
-            let parser = CppParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(LANG::Java) => {
-            let parser = PreprocParser::new(contents, &path_buf, None);
-            rust_code_analysis::metrics(&parser, path_buf)
-        }
-        Some(_) => None,
-        None => None,
+/// The path of `path` relative to the root of the repository, the way its history records it.
+fn repo_path(
+    path: &Path,
+    base_dir: &Path,
+    revision: Option<&str>,
+    scope: Option<&RawPath>,
+) -> RawPath {
+    // Files read from a revision are already relative to the root of the repository.
+    let relative = RawPath::from_path(path.strip_prefix(base_dir).unwrap_or(path));
+    match (revision, scope) {
+        (None, Some(scope)) => scope.join(&relative),
+        _ => relative,
     }
 }
 
-fn setup_file_walker(
-    base_dir: &Path,
-) -> FilterMap<GlobWalker, fn(Result<DirEntry, WalkError>) -> Option<DirEntry>> {
-    globwalk::GlobWalkerBuilder::from_patterns(base_dir, &FILE_GLOBS)
+fn get_metrics(
+    registry: &AnalyzerRegistry,
+    cache: Option<&BlobCache>,
+    path: &Path,
+) -> Option<SpecificMetrics> {
+    let contents = fs::read(path).ok()?;
+    Some(registry.analyze_cached(path, &contents, cache))
+}
+
+type FileWalker = FilterMap<GlobWalker, fn(Result<DirEntry, WalkError>) -> Option<DirEntry>>;
+
+fn setup_file_walker(base_dir: &Path) -> Result<FileWalker, HotspotError> {
+    let walker = globwalk::GlobWalkerBuilder::from_patterns(base_dir, &FILE_GLOBS)
         .build()
-        .unwrap()
-        .into_iter()
-        .filter_map(Result::ok)
+        .map_err(|e| HotspotError::InvalidConfig(format!("invalid file pattern: {}", e)))?;
+    Ok(walker.filter_map(Result::ok))
 }
 
-fn output<I>(config: MetricsConfig, metrics: I)
-where
-    I: IntoIterator<Item = SpecificMetrics>,
-{
-    let mut table = term_table::Table::new();
-    table.max_column_width = 400;
-    table.style = TableStyle::thin();
-    table.add_row(term_table::row::Row::new(vec![
-        term_table::table_cell::TableCell::new("File"),
-        term_table::table_cell::TableCell::new("Lines"),
-        term_table::table_cell::TableCell::new("Cognitive"),
-        term_table::table_cell::TableCell::new("Cyclomatic"),
-    ]));
+/// Cognitive complexity of a file that is shown as high, and as very high.
+const HIGH_COGNITIVE: i64 = 50;
+const VERY_HIGH_COGNITIVE: i64 = 100;
+
+fn complexity(cognitive: Option<i64>) -> Highlight {
+    match cognitive {
+        Some(c) if c >= VERY_HIGH_COGNITIVE => Highlight::Danger,
+        Some(c) if c >= HIGH_COGNITIVE => Highlight::Warning,
+        _ => Highlight::None,
+    }
+}
+
+fn output(config: MetricsConfig, metrics: Vec<SpecificMetrics>) {
+    let mut table = Table::new()
+        .path("File")
+        .right("Lines")
+        .right("Cognitive")
+        .right("Cyclomatic")
+        .column("Analyzer");
     for m in metrics {
         if m.loc.is_some() {
-            let loc = m.loc.map(|x| x.to_string()).unwrap_or(String::new());
-            let cog = m.cognitive.map(|x| x.to_string()).unwrap_or(String::new());
-            let cyc = m.cyclomatic.map(|x| x.to_string()).unwrap_or(String::new());
-            table.add_row(term_table::row::Row::new(vec![
-                term_table::table_cell::TableCell::new(truncate_left(m.path, 80)),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    loc,
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    cog,
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    cyc,
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-            ]));
+            let loc = m.loc.map(|x| x.to_string()).unwrap_or_default();
+            let cog = Cell::new(
+                m.cognitive.map(|x| x.to_string()).unwrap_or_default(),
+                complexity(m.cognitive),
+            );
+            let cyc = m.cyclomatic.map(|x| x.to_string()).unwrap_or_default();
+            table.add_row(vec![
+                m.path.to_string().into(),
+                loc.into(),
+                cog,
+                cyc.into(),
+                m.analyzer.unwrap_or_default().into(),
+            ]);
         } else if config.verbosity.is_verbose() {
-            table.add_row(term_table::row::Row::new(vec![
-                term_table::table_cell::TableCell::new(truncate_left(m.path, 80)),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    "-",
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    "-",
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-                term_table::table_cell::TableCell::new_with_alignment(
-                    "-",
-                    1,
-                    term_table::table_cell::Alignment::Right,
-                ),
-            ]));
+            table.add_row(vec![
+                Cell::from(m.path.to_string()),
+                "-".into(),
+                "-".into(),
+                "-".into(),
+                "-".into(),
+            ]);
         }
     }
-    println!("{}", table.render());
+    println!("{}", table.render(config.format));
+}
+
+fn output_groups(
+    format: OutputFormat,
+    rollup: &Rollup<MetricsTotal>,
+    grouping: Grouping,
+    files: usize,
+) {
+    if let OutputFormat::Markdown { .. } = format {
+        table::print_line(
+            format,
+            &format!("{} files in {} groups", files, rollup.len()),
+        );
+    }
+    let mut table = Table::new()
+        .path(match grouping {
+            Grouping::Component => "Component",
+            _ => "Directory",
+        })
+        .right("Files")
+        .right("Lines")
+        .right("Cognitive")
+        .right("Avg cognitive")
+        .right("Max cognitive")
+        .right("Cyclomatic")
+        .right("Max cyclomatic");
+    for (name, total) in rollup.groups() {
+        table.add_row(vec![
+            Cell::from(name),
+            total.files.to_string().into(),
+            total.loc.to_string().into(),
+            total.cognitive.to_string().into(),
+            format!("{:.1}", total.average_cognitive()).into(),
+            Cell::new(total.max_cognitive, complexity(Some(total.max_cognitive))),
+            total.cyclomatic.to_string().into(),
+            total.max_cyclomatic.to_string().into(),
+        ]);
+    }
+    println!("{}", table.render(format));
+}
+
+/// `m` with its path relative to `root` when it is inside it.
+fn relative_to(root: Option<&Path>, mut m: SpecificMetrics) -> SpecificMetrics {
+    if let Some(relative) = root.and_then(|root| {
+        m.path
+            .to_path_buf()
+            .strip_prefix(root)
+            .ok()
+            .map(RawPath::from_path)
+    }) {
+        m.path = relative;
+    }
+    m
+}
+
+/// The table of analyzed files with paths relative to `root`, after a line summing them up.
+fn output_markdown(config: MetricsConfig, metrics: Vec<SpecificMetrics>, root: Option<&Path>) {
+    let analyzed: Vec<SpecificMetrics> = metrics
+        .into_iter()
+        .filter(|m| m.loc.is_some())
+        .map(|m| relative_to(root, m))
+        .collect();
+    table::print_line(
+        config.format,
+        &format!(
+            "{} files, {} lines, cognitive complexity {}, cyclomatic complexity {}",
+            analyzed.len(),
+            analyzed.iter().filter_map(|m| m.loc).sum::<i64>(),
+            analyzed.iter().filter_map(|m| m.cognitive).sum::<i64>(),
+            analyzed.iter().filter_map(|m| m.cyclomatic).sum::<i64>()
+        ),
+    );
+    output(config, analyzed);
+}
+
+fn output_sarif(violations: &[Violation], hotspots: &[&Hotspot], root: Option<&Path>) {
+    let mut report = SarifReport::new();
+    for v in violations {
+        report.push_violation(v);
+    }
+    for h in hotspots {
+        report.push_hotspot(h);
+    }
+    println!("{}", report.render(root));
 }
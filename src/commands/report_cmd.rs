@@ -0,0 +1,69 @@
+use crate::commands::{save_cache, thread_pool};
+use hotspot::activity::Activity;
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache, CommitCache};
+use hotspot::error::HotspotError;
+use hotspot::history;
+use hotspot::html::{self, ReportHeader};
+use hotspot::shared_types::{supported_files, ReportConfig};
+use hotspot::snapshot;
+use std::fs;
+use std::path::Path;
+
+pub fn execute(config: ReportConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() {
+        println!("Executing report command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    let commit = repo
+        .revparse_single(&config.revision)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|source| HotspotError::BadRevision {
+            revision: config.revision.clone(),
+            source,
+        })?;
+    let (blob_cache, commit_cache) = if config.use_cache {
+        let dir = cache_dir(&repo);
+        (BlobCache::open(&dir).ok(), CommitCache::open(&dir).ok())
+    } else {
+        (None, None)
+    };
+    let pool = thread_pool(config.jobs)?;
+
+    let registry = AnalyzerRegistry::default();
+    let files = snapshot::files_at(&repo, &config.revision, scope.as_ref())?;
+    let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+    let supported = supported_files();
+    let mut activity = Activity::collect(
+        history::walk_from(&repo, &config.revision, &config.history)?
+            .with_cache(commit_cache.as_ref())
+            .within(scope),
+    )?;
+    activity
+        .files
+        .retain(|path, _| supported.is_match(path.to_string_lossy()));
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+    save_cache(commit_cache);
+
+    let header = ReportHeader {
+        repository: config.repository_path.clone(),
+        revision: config.revision.clone(),
+        commit: commit.id().to_string(),
+    };
+    let path = Path::new(&config.html);
+    fs::write(path, html::render(&header, &metrics, &activity))
+        .map_err(|e| HotspotError::io(path, e))?;
+    if config.verbosity.is_not_quiet() {
+        println!("Report written to: {}", path.display());
+        println!("Report command completed in: {}s", time_taken_sec);
+    }
+    Ok(())
+}
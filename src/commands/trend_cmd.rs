@@ -0,0 +1,139 @@
+use crate::commands::{save_cache, thread_pool};
+use hotspot::analyzers::AnalyzerRegistry;
+use hotspot::cache::{cache_dir, BlobCache};
+use hotspot::error::HotspotError;
+use hotspot::shared_types::{OutputFormat, SpecificMetrics, TrendConfig};
+use hotspot::table::{self, Table};
+use hotspot::trend::{self, TrendPoint};
+use hotspot::{history, snapshot};
+use std::path::Path;
+
+extern crate chrono;
+use chrono::prelude::*;
+
+pub fn execute(config: TrendConfig) -> Result<(), HotspotError> {
+    if config.verbosity.is_not_quiet() && config.format.is_table() {
+        println!("Executing trend command...");
+        println!("Verbosity: {}", config.verbosity);
+        println!("Path: {}", config.repository_path);
+    }
+
+    let timer = eggtimer::Timer::start();
+
+    let registry = AnalyzerRegistry::default();
+    let base_dir = Path::new(&config.repository_path);
+    let repo = history::open_repository(base_dir)?;
+    let scope = history::scope_of(&repo, base_dir);
+    // Most files are unchanged between samples, so the cache saves parsing them again.
+    let blob_cache = if config.use_cache {
+        BlobCache::open(&cache_dir(&repo)).ok()
+    } else {
+        None
+    };
+
+    let pool = thread_pool(config.jobs)?;
+    let mut points = vec![];
+    for sample in trend::samples(&repo, config.interval)? {
+        let files = snapshot::files_at(&repo, &sample.oid.to_string(), scope.as_ref())?;
+        let metrics = snapshot::measure(&pool, &repo, &files, &registry, blob_cache.as_ref())?;
+        points.push(TrendPoint {
+            sample,
+            files: metrics,
+        });
+    }
+    let time_taken_sec = timer.elapsed();
+    save_cache(blob_cache);
+
+    let v = config.verbosity.is_not_quiet() && config.format.is_table();
+    let samples_taken = points.len();
+    output(config, points);
+    if v {
+        println!("Samples taken: {}", samples_taken);
+        println!("Trend command completed in: {}s", time_taken_sec);
+    }
+    Ok(())
+}
+
+fn date(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|t| t.date_naive().to_string())
+        .unwrap_or_default()
+}
+
+fn output(config: TrendConfig, points: Vec<TrendPoint>) {
+    if let (OutputFormat::Markdown { .. }, Some(first), Some(last)) =
+        (config.format, points.first(), points.last())
+    {
+        table::print_line(
+            config.format,
+            &format!(
+                "{} samples from {} to {}, cognitive complexity {} -> {}",
+                points.len(),
+                date(first.sample.time),
+                date(last.sample.time),
+                first.cognitive(),
+                last.cognitive()
+            ),
+        );
+    }
+    let mut table = Table::new()
+        .column("Date")
+        .column("Revision")
+        .right("Files")
+        .right("Lines")
+        .right("Cognitive")
+        .right("Cyclomatic")
+        .right("Cognitive change");
+    let mut previous: Option<i64> = None;
+    for point in &points {
+        let cognitive = point.cognitive();
+        let change = previous
+            .map(|p| format!("{:+}", cognitive - p))
+            .unwrap_or_default();
+        previous = Some(cognitive);
+        table.add_row(vec![
+            date(point.sample.time),
+            point.sample.label.clone(),
+            point.analyzed_files().to_string(),
+            point.loc().to_string(),
+            cognitive.to_string(),
+            point.cyclomatic().to_string(),
+            change,
+        ]);
+    }
+    println!("{}", table.render(config.format));
+
+    if !config.per_file {
+        return;
+    }
+    let mut rows: Vec<(&SpecificMetrics, &TrendPoint)> = points
+        .iter()
+        .flat_map(|point| {
+            point
+                .files
+                .iter()
+                .filter(|f| f.loc.is_some())
+                .map(move |f| (f, point))
+        })
+        .collect();
+    // Grouped by file, then in the order the samples were taken.
+    rows.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    let mut table = Table::new()
+        .path("File")
+        .column("Date")
+        .column("Revision")
+        .right("Lines")
+        .right("Cognitive")
+        .right("Cyclomatic");
+    for (file, point) in rows {
+        table.add_row(vec![
+            file.path.to_string(),
+            date(point.sample.time),
+            point.sample.label.clone(),
+            file.loc.unwrap_or_default().to_string(),
+            file.cognitive.unwrap_or_default().to_string(),
+            file.cyclomatic.unwrap_or_default().to_string(),
+        ]);
+    }
+    println!("{}", table.render(config.format));
+}
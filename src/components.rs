@@ -0,0 +1,266 @@
+//! Named components of a repository, eg. the services of a monorepo, matched by the globs in the
+//! `[components]` table of the project configuration, and how each of them has been changed.
+
+use crate::config::ProjectConfig;
+use crate::error::HotspotError;
+use crate::history::CommitRecord;
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::Repository;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Matches paths relative to the repository root to components, in name order. A path may be in
+/// more than one component, or in none.
+#[derive(Debug, Clone, Default)]
+pub struct Components {
+    components: Vec<(String, GlobSet)>,
+}
+
+impl Components {
+    /// The components of the configuration at `path`, or of the one in the root of `repo`, failing
+    /// when it names none.
+    pub fn load(path: Option<&Path>, repo: &Repository) -> Result<Self, HotspotError> {
+        let components = ProjectConfig::load(path, repo)?.components()?;
+        if components.is_empty() {
+            return Err(HotspotError::InvalidConfig(
+                "no components are set in the [components] table of the configuration".to_string(),
+            ));
+        }
+        Ok(components)
+    }
+
+    pub fn add(&mut self, name: &str, globs: &[String]) -> Result<(), HotspotError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in globs {
+            let glob = Glob::new(pattern).map_err(|e| {
+                HotspotError::InvalidConfig(format!(
+                    "component {}: invalid path '{}': {}",
+                    name, pattern, e
+                ))
+            })?;
+            builder.add(glob);
+        }
+        let globs = builder
+            .build()
+            .map_err(|e| HotspotError::InvalidConfig(format!("component {}: {}", name, e)))?;
+        self.components.push((name.to_string(), globs));
+        self.components.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(())
+    }
+
+    /// The names of the components `path` is in.
+    pub fn of<'a>(&'a self, path: &RawPath) -> impl Iterator<Item = &'a str> + 'a {
+        let path = path.to_string_lossy();
+        self.components
+            .iter()
+            .filter(move |(_, globs)| globs.is_match(&path))
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.components.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+/// Who changed a component's files.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentActivity {
+    /// Commits that changed any of its files.
+    pub commits: u32,
+    /// Number of those commits each person made.
+    pub contributors: HashMap<ContributorKey, u32>,
+    /// Everyone who changed each file.
+    pub files: HashMap<RawPath, HashSet<ContributorKey>>,
+}
+
+impl ComponentActivity {
+    /// The `n` people with the most commits, most first.
+    pub fn top_contributors(&self, n: usize) -> Vec<(&ContributorKey, u32)> {
+        let mut contributors: Vec<(&ContributorKey, u32)> =
+            self.contributors.iter().map(|(c, n)| (c, *n)).collect();
+        contributors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.email().cmp(b.0.email())));
+        contributors.truncate(n);
+        contributors
+    }
+
+    pub fn truck_factor(&self) -> usize {
+        truck_factor(&self.files)
+    }
+}
+
+/// How each component changed and which ones changed in the same commits.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentHistory {
+    /// Every component, including those nothing has changed yet.
+    pub components: BTreeMap<String, ComponentActivity>,
+    /// Commits that changed both of a pair of components, the pair in name order.
+    pub co_changes: BTreeMap<(String, String), u32>,
+}
+
+impl ComponentHistory {
+    pub fn new(components: &Components) -> Self {
+        ComponentHistory {
+            components: components
+                .names()
+                .map(|name| (name.to_string(), ComponentActivity::default()))
+                .collect(),
+            co_changes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a commit, in any order.
+    pub fn add(&mut self, components: &Components, record: &CommitRecord) {
+        let mut changed = BTreeSet::new();
+        for delta in &record.deltas {
+            for name in components.of(&delta.path) {
+                changed.insert(name);
+                if let Some(activity) = self.components.get_mut(name) {
+                    activity
+                        .files
+                        .entry(delta.path.clone())
+                        .or_default()
+                        .insert(record.author.clone());
+                }
+            }
+        }
+        for name in &changed {
+            if let Some(activity) = self.components.get_mut(*name) {
+                activity.commits += 1;
+                *activity
+                    .contributors
+                    .entry(record.author.clone())
+                    .or_insert(0) += 1;
+            }
+        }
+        let changed: Vec<&str> = changed.into_iter().collect();
+        for (i, a) in changed.iter().enumerate() {
+            for b in &changed[i + 1..] {
+                *self
+                    .co_changes
+                    .entry((a.to_string(), b.to_string()))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+/// The fewest people who, were they to leave, would leave more than half of the files with no one
+/// who has changed them. The people who changed the most of the files still known are taken first.
+pub fn truck_factor(files: &HashMap<RawPath, HashSet<ContributorKey>>) -> usize {
+    let mut gone: HashSet<&ContributorKey> = HashSet::new();
+    loop {
+        let known: Vec<&HashSet<ContributorKey>> = files
+            .values()
+            .filter(|cs| cs.iter().any(|c| !gone.contains(c)))
+            .collect();
+        if known.len() * 2 < files.len() || known.is_empty() {
+            return gone.len();
+        }
+        let mut counts: HashMap<&ContributorKey, usize> = HashMap::new();
+        for c in known.iter().flat_map(|cs| cs.iter()) {
+            if !gone.contains(c) {
+                *counts.entry(c).or_insert(0) += 1;
+            }
+        }
+        let most = counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.email().cmp(a.0.email())))
+            .map(|(c, _)| c);
+        match most {
+            Some(c) => gone.insert(c),
+            None => return gone.len(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{truck_factor, ComponentHistory, Components};
+    use crate::history::{CommitRecord, FileDelta};
+    use crate::shared_types::{ContributorKey, RawPath};
+    use git2::{Delta, Oid};
+    use std::collections::{HashMap, HashSet};
+
+    fn person(name: &str) -> ContributorKey {
+        ContributorKey::new(format!("{}@example.com", name), name.to_string())
+    }
+
+    fn commit(author: &str, paths: &[&str]) -> CommitRecord {
+        CommitRecord {
+            oid: Oid::zero(),
+            author: person(author),
+            time: 0,
+            deltas: paths
+                .iter()
+                .map(|p| FileDelta {
+                    path: RawPath::from(*p),
+                    status: Delta::Modified,
+                    additions: 1,
+                    deletions: 0,
+                })
+                .collect(),
+        }
+    }
+
+    fn components() -> Components {
+        let mut components = Components::default();
+        components
+            .add("billing", &["services/billing/**".to_string()])
+            .unwrap();
+        components.add("web", &["apps/web/**".to_string()]).unwrap();
+        components.add("docs", &["docs/**".to_string()]).unwrap();
+        components
+    }
+
+    #[test]
+    fn commits_count_once_per_component_and_pair() {
+        let components = components();
+        let mut history = ComponentHistory::new(&components);
+        for record in [
+            commit("alice", &["services/billing/a.rs", "services/billing/b.rs"]),
+            commit("bob", &["services/billing/a.rs", "apps/web/app.ts"]),
+            commit("bob", &["apps/web/app.ts", "README.md"]),
+        ] {
+            history.add(&components, &record);
+        }
+        let billing = &history.components["billing"];
+        assert_eq!(billing.commits, 2);
+        assert_eq!(billing.files.len(), 2);
+        assert_eq!(billing.top_contributors(1)[0].0.name(), "alice");
+        assert_eq!(history.components["web"].commits, 2);
+        assert_eq!(history.components["docs"].commits, 0);
+        let pairs: Vec<(&str, &str, u32)> = history
+            .co_changes
+            .iter()
+            .map(|((a, b), n)| (a.as_str(), b.as_str(), *n))
+            .collect();
+        assert_eq!(pairs, vec![("billing", "web", 1)]);
+    }
+
+    #[test]
+    fn truck_factor_is_who_must_leave_to_orphan_most_files() {
+        let files = |owners: &[&[&str]]| -> HashMap<RawPath, HashSet<ContributorKey>> {
+            owners
+                .iter()
+                .enumerate()
+                .map(|(i, names)| {
+                    (
+                        RawPath::from(format!("{}.rs", i).as_str()),
+                        names.iter().map(|n| person(n)).collect(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(truck_factor(&files(&[])), 0);
+        assert_eq!(truck_factor(&files(&[&["a"], &["a"], &["b"]])), 1);
+        assert_eq!(
+            truck_factor(&files(&[&["a", "b"], &["a", "b"], &["a", "b"], &["c"]])),
+            2
+        );
+    }
+}
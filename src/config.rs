@@ -0,0 +1,342 @@
+//! The project configuration read from `.hotspot.toml` in the root of the repository, or the file
+//! passed with `--config`.
+//!
+//! ```toml
+//! [[rules]]
+//! kind = "function-cognitive"
+//! max = 25
+//!
+//! [[rules]]
+//! kind = "bus-factor"
+//! min = 2
+//! paths = ["src/core/**"]
+//! severity = "warning"
+//!
+//! [components]
+//! billing = "services/billing/**"
+//! web = ["apps/web/**", "packages/ui/**"]
+//!
+//! [teams]
+//! payments = ["*@payments.example.com", "Alice Smith"]
+//! ```
+
+use crate::components::Components;
+use crate::error::HotspotError;
+use crate::teams::Teams;
+use git2::Repository;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = ".hotspot.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Named groups of files, each the globs of its paths relative to the repository root.
+    #[serde(default)]
+    pub components: BTreeMap<String, Paths>,
+    /// Named teams, each the emails or names of its members.
+    #[serde(default)]
+    pub teams: BTreeMap<String, Vec<String>>,
+}
+
+/// One glob, or a list of them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Paths {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Paths {
+    pub fn globs(&self) -> &[String] {
+        match self {
+            Paths::One(glob) => std::slice::from_ref(glob),
+            Paths::Many(globs) => globs,
+        }
+    }
+}
+
+/// What a rule measures. Each kind is either an upper (`max`) or a lower (`min`) limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleKind {
+    /// Cognitive complexity of each function.
+    FunctionCognitive,
+    /// Cyclomatic complexity of each function.
+    FunctionCyclomatic,
+    /// Cognitive complexity of each file.
+    FileCognitive,
+    /// Logical lines of code of each file.
+    FileLoc,
+    /// Cognitive complexity times the number of commits that changed the file.
+    HotspotScore,
+    /// Number of people who have committed to the file.
+    BusFactor,
+}
+
+impl RuleKind {
+    /// The identifier used in reports, eg. `function-cognitive`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            RuleKind::FunctionCognitive => "function-cognitive",
+            RuleKind::FunctionCyclomatic => "function-cyclomatic",
+            RuleKind::FileCognitive => "file-cognitive",
+            RuleKind::FileLoc => "file-loc",
+            RuleKind::HotspotScore => "hotspot-score",
+            RuleKind::BusFactor => "bus-factor",
+        }
+    }
+
+    /// What the rule checks, for reports that describe their rules (eg. SARIF).
+    pub fn description(&self) -> &'static str {
+        match self {
+            RuleKind::FunctionCognitive => "Cognitive complexity of a function is above the limit",
+            RuleKind::FunctionCyclomatic => "Cyclomatic complexity of a function is above the limit",
+            RuleKind::FileCognitive => "Cognitive complexity of a file is above the limit",
+            RuleKind::FileLoc => "Lines of code of a file are above the limit",
+            RuleKind::HotspotScore => {
+                "Cognitive complexity times the number of commits that changed the file is above the limit"
+            }
+            RuleKind::BusFactor => "Fewer people than the minimum have committed to the file",
+        }
+    }
+
+    /// True when the rule sets a minimum rather than a maximum.
+    pub fn is_minimum(&self) -> bool {
+        matches!(self, RuleKind::BusFactor)
+    }
+}
+
+impl fmt::Display for RuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Reported and fails the check.
+    #[default]
+    Error,
+    /// Only reported.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub max: Option<i64>,
+    pub min: Option<i64>,
+    /// Globs of the paths, relative to the repository root, the rule applies to. Empty applies to all.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Rule {
+    pub fn new(kind: RuleKind, limit: i64) -> Self {
+        let (max, min) = if kind.is_minimum() {
+            (None, Some(limit))
+        } else {
+            (Some(limit), None)
+        };
+        Rule {
+            kind,
+            max,
+            min,
+            paths: vec![],
+            severity: Severity::Error,
+        }
+    }
+
+    /// The `max` or `min` the rule checks against, whichever its kind uses.
+    pub fn limit(&self) -> i64 {
+        if self.kind.is_minimum() {
+            self.min.unwrap_or_default()
+        } else {
+            self.max.unwrap_or_default()
+        }
+    }
+
+    /// True when `value` breaks the rule.
+    pub fn is_violated_by(&self, value: i64) -> bool {
+        if self.kind.is_minimum() {
+            value < self.limit()
+        } else {
+            value > self.limit()
+        }
+    }
+
+    pub fn path_matcher(&self) -> Result<Option<GlobSet>, HotspotError> {
+        if self.paths.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.paths {
+            let glob = Glob::new(pattern).map_err(|e| {
+                HotspotError::InvalidConfig(format!(
+                    "rule {}: invalid path '{}': {}",
+                    self.kind, pattern, e
+                ))
+            })?;
+            builder.add(glob);
+        }
+        builder
+            .build()
+            .map(Some)
+            .map_err(|e| HotspotError::InvalidConfig(format!("rule {}: {}", self.kind, e)))
+    }
+
+    fn validate(&self) -> Result<(), HotspotError> {
+        let (needed, given, unused) = if self.kind.is_minimum() {
+            ("min", self.min, self.max)
+        } else {
+            ("max", self.max, self.min)
+        };
+        if given.is_none() || unused.is_some() {
+            return Err(HotspotError::InvalidConfig(format!(
+                "rule {} needs a '{}' and nothing else",
+                self.kind, needed
+            )));
+        }
+        self.path_matcher().map(|_| ())
+    }
+}
+
+impl ProjectConfig {
+    /// The rules used when the configuration has none.
+    pub fn default_rules() -> Vec<Rule> {
+        vec![Rule::new(RuleKind::FunctionCognitive, 25)]
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: ProjectConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        for rule in &config.rules {
+            rule.validate().map_err(|e| e.to_string())?;
+        }
+        config.components().map_err(|e| e.to_string())?;
+        config.teams().map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    /// Matches paths to the components the configuration names.
+    pub fn components(&self) -> Result<Components, HotspotError> {
+        let mut components = Components::default();
+        for (name, paths) in &self.components {
+            components.add(name, paths.globs())?;
+        }
+        Ok(components)
+    }
+
+    /// Matches contributors to the teams the configuration names.
+    pub fn teams(&self) -> Result<Teams, HotspotError> {
+        let mut teams = Teams::default();
+        for (name, members) in &self.teams {
+            teams.add(name, members)?;
+        }
+        Ok(teams)
+    }
+
+    /// Reads the configuration at `path`, or the one in the root of `repo` when `path` is `None`.
+    /// A repository without a configuration file gets the default configuration.
+    pub fn load(path: Option<&Path>, repo: &Repository) -> Result<Self, HotspotError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path(repo) {
+                Some(path) if path.exists() => path,
+                _ => return Ok(ProjectConfig::default()),
+            },
+        };
+        let text = fs::read_to_string(&path).map_err(|e| HotspotError::io(&path, e))?;
+        ProjectConfig::parse(&text)
+            .map_err(|e| HotspotError::InvalidConfig(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// `.hotspot.toml` in the working directory of `repo`, `None` for a bare repository.
+pub fn default_path(repo: &Repository) -> Option<PathBuf> {
+    repo.workdir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{ProjectConfig, RuleKind, Severity};
+    use crate::shared_types::RawPath;
+
+    #[test]
+    fn rules_are_parsed_with_their_limits() {
+        let config = ProjectConfig::parse(
+            r#"
+            [[rules]]
+            kind = "function-cognitive"
+            max = 25
+
+            [[rules]]
+            kind = "bus-factor"
+            min = 2
+            paths = ["src/core/**"]
+            severity = "warning"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].kind, RuleKind::FunctionCognitive);
+        assert!(config.rules[0].is_violated_by(26));
+        assert!(!config.rules[0].is_violated_by(25));
+        assert_eq!(config.rules[1].severity, Severity::Warning);
+        assert!(config.rules[1].is_violated_by(1));
+    }
+
+    #[test]
+    fn a_rule_with_the_wrong_limit_is_rejected() {
+        let result = ProjectConfig::parse(
+            r#"
+            [[rules]]
+            kind = "bus-factor"
+            max = 2
+            "#,
+        );
+        assert!(result.unwrap_err().contains("needs a 'min'"));
+    }
+
+    #[test]
+    fn components_take_one_glob_or_a_list() {
+        let config = ProjectConfig::parse(
+            r#"
+            [components]
+            billing = "services/billing/**"
+            web = ["apps/web/**", "packages/ui/**"]
+            "#,
+        )
+        .unwrap();
+        let components = config.components().unwrap();
+        let of = |path: &str| -> Vec<String> {
+            components
+                .of(&RawPath::from(path))
+                .map(String::from)
+                .collect()
+        };
+        assert_eq!(of("services/billing/src/lib.rs"), vec!["billing"]);
+        assert_eq!(of("packages/ui/button.ts"), vec!["web"]);
+        assert!(of("README.md").is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! Compares the metrics of two revisions file by file and function by function.
+
+use crate::analyzers::FunctionMetrics;
+use crate::hotspots::Hotspot;
+use crate::shared_types::{RawPath, SpecificMetrics};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    MoreComplex,
+    LessComplex,
+    /// The size changed but the cognitive complexity did not.
+    Resized,
+}
+
+impl DiffStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiffStatus::Added => "added",
+            DiffStatus::Removed => "removed",
+            DiffStatus::MoreComplex => "more complex",
+            DiffStatus::LessComplex => "less complex",
+            DiffStatus::Resized => "resized",
+        }
+    }
+}
+
+/// A file whose metrics differ between the two revisions. `base` or `head` is `None` when the file does
+/// not exist there.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: RawPath,
+    pub base: Option<SpecificMetrics>,
+    pub head: Option<SpecificMetrics>,
+}
+
+fn delta(
+    base: Option<&SpecificMetrics>,
+    head: Option<&SpecificMetrics>,
+    metric: fn(&SpecificMetrics) -> Option<i64>,
+) -> i64 {
+    let value = |m: Option<&SpecificMetrics>| m.and_then(metric).unwrap_or(0);
+    value(head) - value(base)
+}
+
+impl FileDiff {
+    pub fn status(&self) -> DiffStatus {
+        match (&self.base, &self.head) {
+            (None, _) => DiffStatus::Added,
+            (_, None) => DiffStatus::Removed,
+            _ => match self.cognitive_delta() {
+                d if d > 0 => DiffStatus::MoreComplex,
+                d if d < 0 => DiffStatus::LessComplex,
+                _ => DiffStatus::Resized,
+            },
+        }
+    }
+
+    pub fn loc_delta(&self) -> i64 {
+        delta(self.base.as_ref(), self.head.as_ref(), |m| m.loc)
+    }
+
+    pub fn cognitive_delta(&self) -> i64 {
+        delta(self.base.as_ref(), self.head.as_ref(), |m| m.cognitive)
+    }
+
+    pub fn cyclomatic_delta(&self) -> i64 {
+        delta(self.base.as_ref(), self.head.as_ref(), |m| m.cyclomatic)
+    }
+
+    /// The functions that were added, removed or whose metrics changed. Functions are matched by name,
+    /// and by the order they appear in when several share a name.
+    pub fn functions(&self) -> Vec<FunctionDiff> {
+        let keyed = |m: &Option<SpecificMetrics>| -> BTreeMap<(String, usize), FunctionMetrics> {
+            let mut seen: HashMap<&str, usize> = HashMap::new();
+            let mut keyed = BTreeMap::new();
+            for f in m.iter().flat_map(|m| m.functions.iter()) {
+                let n = seen.entry(f.name.as_str()).or_insert(0);
+                keyed.insert((f.name.clone(), *n), f.clone());
+                *n += 1;
+            }
+            keyed
+        };
+        let mut base = keyed(&self.base);
+        let head = keyed(&self.head);
+        let mut functions = vec![];
+        for (key, head) in head {
+            let base = base.remove(&key);
+            let unchanged = base.as_ref().is_some_and(|b| {
+                (b.loc, b.cognitive, b.cyclomatic) == (head.loc, head.cognitive, head.cyclomatic)
+            });
+            if !unchanged {
+                functions.push(FunctionDiff {
+                    name: key.0,
+                    base,
+                    head: Some(head),
+                });
+            }
+        }
+        for ((name, _), base) in base {
+            functions.push(FunctionDiff {
+                name,
+                base: Some(base),
+                head: None,
+            });
+        }
+        functions.sort_by(|a, b| (a.start_line(), &a.name).cmp(&(b.start_line(), &b.name)));
+        functions
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDiff {
+    pub name: String,
+    pub base: Option<FunctionMetrics>,
+    pub head: Option<FunctionMetrics>,
+}
+
+impl FunctionDiff {
+    /// Where the function starts in `head`, or in `base` when it was removed.
+    pub fn start_line(&self) -> usize {
+        self.head
+            .as_ref()
+            .or(self.base.as_ref())
+            .map(|f| f.start_line)
+            .unwrap_or(0)
+    }
+
+    pub fn cognitive_delta(&self) -> i64 {
+        let value = |f: &Option<FunctionMetrics>| f.as_ref().map(|f| f.cognitive).unwrap_or(0);
+        value(&self.head) - value(&self.base)
+    }
+
+    pub fn loc_delta(&self) -> i64 {
+        let value = |f: &Option<FunctionMetrics>| f.as_ref().map(|f| f.loc).unwrap_or(0);
+        value(&self.head) - value(&self.base)
+    }
+}
+
+/// The metrics of each function, leaving out where it is in the file.
+fn measured_functions(m: &SpecificMetrics) -> Vec<(&str, i64, i64, i64)> {
+    let mut functions: Vec<(&str, i64, i64, i64)> = m
+        .functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.loc, f.cognitive, f.cyclomatic))
+        .collect();
+    functions.sort_unstable();
+    functions
+}
+
+/// Functions are compared by name and metrics, so a file whose functions only moved is unchanged.
+fn same_metrics(a: &SpecificMetrics, b: &SpecificMetrics) -> bool {
+    (a.loc, a.cognitive, a.cyclomatic) == (b.loc, b.cognitive, b.cyclomatic)
+        && measured_functions(a) == measured_functions(b)
+}
+
+/// The files that were added, removed or whose metrics changed, ordered by path.
+pub fn compare(base: Vec<SpecificMetrics>, head: Vec<SpecificMetrics>) -> Vec<FileDiff> {
+    let mut base: HashMap<RawPath, SpecificMetrics> =
+        base.into_iter().map(|m| (m.path.clone(), m)).collect();
+    let mut diffs = vec![];
+    for head in head {
+        match base.remove(&head.path) {
+            Some(base) if same_metrics(&base, &head) => {}
+            base => diffs.push(FileDiff {
+                path: head.path.clone(),
+                base,
+                head: Some(head),
+            }),
+        }
+    }
+    for (path, base) in base {
+        diffs.push(FileDiff {
+            path,
+            base: Some(base),
+            head: None,
+        });
+    }
+    diffs.sort_by(|a, b| a.path.cmp(&b.path));
+    diffs
+}
+
+/// Files that are among the `top` hotspots in one revision but not the other.
+pub struct HotspotChanges {
+    /// Hotspots of `head` that were not hotspots in `base`, with their scores in `head`.
+    pub introduced: Vec<Hotspot>,
+    /// Hotspots of `base` that are no longer hotspots in `head`, with their scores in `base`.
+    pub removed: Vec<Hotspot>,
+}
+
+/// Compares the `top` entries of two rankings from [`crate::hotspots::rank`]. Files with a score of
+/// zero are never hotspots.
+pub fn hotspot_changes(base: &[Hotspot], head: &[Hotspot], top: usize) -> HotspotChanges {
+    let hottest = |ranked: &[Hotspot]| -> Vec<Hotspot> {
+        ranked
+            .iter()
+            .filter(|h| h.score > 0)
+            .take(top)
+            .cloned()
+            .collect()
+    };
+    let base = hottest(base);
+    let head = hottest(head);
+    HotspotChanges {
+        introduced: head
+            .iter()
+            .filter(|h| !base.iter().any(|b| b.path == h.path))
+            .cloned()
+            .collect(),
+        removed: base
+            .iter()
+            .filter(|b| !head.iter().any(|h| h.path == b.path))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzers::{FileMetrics, FunctionMetrics};
+    use crate::diff::{compare, hotspot_changes, DiffStatus};
+    use crate::hotspots::Hotspot;
+    use crate::shared_types::{RawPath, SpecificMetrics};
+
+    fn function(name: &str, start_line: usize, cognitive: i64) -> FunctionMetrics {
+        FunctionMetrics {
+            name: name.to_string(),
+            start_line,
+            end_line: start_line + 5,
+            loc: 5,
+            cognitive,
+            cyclomatic: 1,
+        }
+    }
+
+    fn file(path: &str, loc: i64, functions: Vec<FunctionMetrics>) -> SpecificMetrics {
+        let metrics = FileMetrics {
+            loc,
+            cognitive: functions.iter().map(|f| f.cognitive).sum(),
+            cyclomatic: 1,
+            functions,
+        };
+        SpecificMetrics::analyzed(RawPath::from(path), "test", metrics)
+    }
+
+    #[test]
+    fn files_and_functions_are_compared_between_revisions() {
+        let base = vec![
+            file("same.rs", 10, vec![function("a", 1, 1)]),
+            file(
+                "grown.rs",
+                10,
+                vec![function("a", 1, 1), function("b", 8, 2)],
+            ),
+            file("gone.rs", 5, vec![]),
+            file(
+                "moved.rs",
+                10,
+                vec![function("a", 1, 1), function("b", 8, 2)],
+            ),
+        ];
+        let head = vec![
+            file("same.rs", 10, vec![function("a", 1, 1)]),
+            file(
+                "grown.rs",
+                20,
+                vec![function("a", 1, 4), function("c", 8, 1)],
+            ),
+            file("new.rs", 3, vec![]),
+            file(
+                "moved.rs",
+                10,
+                vec![function("b", 2, 2), function("a", 9, 1)],
+            ),
+        ];
+        let diffs = compare(base, head);
+        let summary: Vec<(String, DiffStatus, i64)> = diffs
+            .iter()
+            .map(|d| (d.path.to_string(), d.status(), d.loc_delta()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("gone.rs".to_string(), DiffStatus::Removed, -5),
+                ("grown.rs".to_string(), DiffStatus::MoreComplex, 10),
+                ("new.rs".to_string(), DiffStatus::Added, 3),
+            ]
+        );
+
+        let functions: Vec<(String, i64)> = diffs[1]
+            .functions()
+            .into_iter()
+            .map(|f| (f.name.clone(), f.cognitive_delta()))
+            .collect();
+        assert_eq!(
+            functions,
+            vec![
+                ("a".to_string(), 3),
+                ("b".to_string(), -2),
+                ("c".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn hotspots_entering_or_leaving_the_top_are_reported() {
+        let hotspot = |path: &str, score: i64| Hotspot {
+            path: RawPath::from(path),
+            touches: 1,
+            cognitive: score,
+            loc: 1,
+            score,
+        };
+        let base = vec![
+            hotspot("a.rs", 50),
+            hotspot("b.rs", 40),
+            hotspot("c.rs", 30),
+        ];
+        let head = vec![
+            hotspot("c.rs", 90),
+            hotspot("a.rs", 50),
+            hotspot("b.rs", 40),
+        ];
+        let changes = hotspot_changes(&base, &head, 2);
+        assert_eq!(changes.introduced, vec![hotspot("c.rs", 90)]);
+        assert_eq!(changes.removed, vec![hotspot("b.rs", 40)]);
+    }
+}
@@ -0,0 +1,86 @@
+//! The errors a command can fail with, each mapped to its own process exit code.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum HotspotError {
+    /// The path is not inside a git repository.
+    NotARepository { path: PathBuf, source: git2::Error },
+    /// A revision (`HEAD`, a tag, a commit id, ...) could not be resolved.
+    BadRevision {
+        revision: String,
+        source: git2::Error,
+    },
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// The arguments or configuration are invalid.
+    InvalidConfig(String),
+    /// Any other failure while reading the git repository.
+    Git(git2::Error),
+    /// The `check` command found this many rule violations with error severity.
+    ChecksFailed(usize),
+}
+
+impl HotspotError {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        HotspotError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// The process exit code for this error. `0` is success and `1` is used for unexpected git failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HotspotError::Git(_) => 1,
+            HotspotError::InvalidConfig(_) => 2,
+            HotspotError::NotARepository { .. } => 3,
+            HotspotError::BadRevision { .. } => 4,
+            HotspotError::Io { .. } => 5,
+            HotspotError::ChecksFailed(_) => 6,
+        }
+    }
+}
+
+impl fmt::Display for HotspotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HotspotError::NotARepository { path, .. } => {
+                write!(f, "'{}' is not inside a git repository", path.display())
+            }
+            HotspotError::BadRevision { revision, source } => {
+                write!(
+                    f,
+                    "could not resolve revision '{}': {}",
+                    revision,
+                    source.message()
+                )
+            }
+            HotspotError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            HotspotError::InvalidConfig(message) => write!(f, "{}", message),
+            HotspotError::Git(e) => write!(f, "git error: {}", e.message()),
+            HotspotError::ChecksFailed(1) => write!(f, "1 rule violation"),
+            HotspotError::ChecksFailed(count) => write!(f, "{} rule violations", count),
+        }
+    }
+}
+
+impl std::error::Error for HotspotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HotspotError::NotARepository { source, .. } => Some(source),
+            HotspotError::BadRevision { source, .. } => Some(source),
+            HotspotError::Io { source, .. } => Some(source),
+            HotspotError::InvalidConfig(_) | HotspotError::ChecksFailed(_) => None,
+            HotspotError::Git(e) => Some(e),
+        }
+    }
+}
+
+impl From<git2::Error> for HotspotError {
+    fn from(e: git2::Error) -> Self {
+        HotspotError::Git(e)
+    }
+}
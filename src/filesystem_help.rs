@@ -8,7 +8,7 @@ pub fn setup_file_walker(
     globwalk::GlobWalkerBuilder::from_patterns(
         base_dir,
         &[
-            "*.{cs,csx,c,cpp,fs,fsx,go,js,java,py,rs,ts,tsx}",
+            "*.{cs,csx,c,cpp,fs,fsi,fsx,go,js,java,py,rs,ts,tsx}",
             "!.*",
             "!node_modules/",
             "!target/",
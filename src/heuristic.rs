@@ -0,0 +1,284 @@
+//! Line based metrics for languages that rust-code-analysis has no grammar (or no metrics) for.
+//!
+//! The numbers are an approximation and are calculated as follows:
+//!
+//! - **Lines**: physical lines that contain code once comments and blank lines are removed.
+//! - **Cyclomatic**: 1 plus one for every branching keyword (`if`, `for`, `while`, `case`, `catch`, ...),
+//!   F# match arm (`| pattern ->`) and boolean operator (`&&`, `||`). Ternaries are not counted.
+//! - **Cognitive**: every control structure scores 1 plus its nesting level, `else`/`else if`/`elif`
+//!   score 1 and each sequence of mixed boolean operators on a line scores 1. Nesting is tracked with
+//!   braces for C-like languages and with indentation for F#.
+//!
+//! Comments and string literals are stripped before counting so keywords inside them are ignored.
+
+/// The language family used to strip comments and recognise keywords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    CSharp,
+    FSharp,
+    Go,
+    Java,
+}
+
+impl Dialect {
+    pub fn from_ext(ext: &str) -> Option<Dialect> {
+        match ext {
+            "cs" | "csx" => Some(Dialect::CSharp),
+            "fs" | "fsi" | "fsx" => Some(Dialect::FSharp),
+            "go" => Some(Dialect::Go),
+            "java" => Some(Dialect::Java),
+            _ => None,
+        }
+    }
+
+    fn nesting_keywords(&self) -> &'static [&'static str] {
+        match self {
+            Dialect::CSharp => &["if", "for", "foreach", "while", "switch", "catch"],
+            Dialect::FSharp => &["if", "for", "while", "match"],
+            Dialect::Go => &["if", "for", "switch", "select"],
+            Dialect::Java => &["if", "for", "while", "switch", "catch"],
+        }
+    }
+
+    fn branch_keywords(&self) -> &'static [&'static str] {
+        match self {
+            Dialect::CSharp => &["if", "for", "foreach", "while", "case", "catch"],
+            Dialect::FSharp => &["if", "elif", "for", "while"],
+            Dialect::Go => &["if", "for", "case"],
+            Dialect::Java => &["if", "for", "while", "case", "catch"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeuristicMetrics {
+    pub loc: i64,
+    pub cognitive: i64,
+    pub cyclomatic: i64,
+}
+
+/// Calculates approximate metrics for `contents`. Invalid UTF-8 is replaced rather than rejected.
+pub fn measure(contents: &[u8], dialect: Dialect) -> HeuristicMetrics {
+    let text = String::from_utf8_lossy(contents);
+    let lines = strip_comments_and_strings(&text, dialect);
+
+    let mut metrics = HeuristicMetrics {
+        loc: 0,
+        cognitive: 0,
+        cyclomatic: 1,
+    };
+    // For C-like languages each open brace records whether it belongs to a control structure.
+    let mut braces: Vec<bool> = Vec::new();
+    let mut pending_control = false;
+    // For F# each entry is the indentation of an open control structure.
+    let mut indents: Vec<usize> = Vec::new();
+
+    for line in &lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        metrics.loc += 1;
+
+        if dialect == Dialect::FSharp {
+            let indent = line.len() - line.trim_start().len();
+            let trimmed = line.trim_start();
+            // Match arms sit at the same indentation as their `match` so must not close it.
+            let is_arm = trimmed.starts_with('|');
+            while indents
+                .last()
+                .is_some_and(|i| *i > indent || (*i == indent && !is_arm))
+            {
+                indents.pop();
+            }
+            if is_arm && trimmed.contains("->") {
+                metrics.cyclomatic += 1;
+            }
+        }
+
+        let tokens = tokenize(line);
+        let mut previous_word: Option<&str> = None;
+        let mut last_bool_op: Option<&str> = None;
+        for token in &tokens {
+            let token = token.as_str();
+            match token {
+                "{" if dialect != Dialect::FSharp => {
+                    braces.push(pending_control);
+                    pending_control = false;
+                }
+                "}" if dialect != Dialect::FSharp => {
+                    braces.pop();
+                }
+                ";" => pending_control = false,
+                "&&" | "||" => {
+                    metrics.cyclomatic += 1;
+                    if last_bool_op != Some(token) {
+                        metrics.cognitive += 1;
+                    }
+                    last_bool_op = Some(token);
+                }
+                _ => {
+                    let nesting = match dialect {
+                        Dialect::FSharp => indents.len(),
+                        _ => braces.iter().filter(|c| **c).count(),
+                    } as i64;
+                    let is_else_if = previous_word == Some("else") && token == "if";
+                    if dialect.branch_keywords().contains(&token) {
+                        metrics.cyclomatic += 1;
+                    }
+                    if token == "else" || token == "elif" {
+                        metrics.cognitive += 1;
+                    } else if dialect.nesting_keywords().contains(&token) && !is_else_if {
+                        metrics.cognitive += 1 + nesting;
+                    }
+                    if token == "else"
+                        || token == "try"
+                        || token == "do"
+                        || dialect.nesting_keywords().contains(&token)
+                    {
+                        pending_control = true;
+                        if dialect == Dialect::FSharp && !is_else_if {
+                            indents.push(line.len() - line.trim_start().len());
+                        }
+                    }
+                    if token.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        previous_word = Some(token);
+                    }
+                }
+            }
+        }
+    }
+    metrics
+}
+
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+        match c {
+            '&' | '|' if chars.peek() == Some(&c) => {
+                chars.next();
+                tokens.push(format!("{}{}", c, c));
+            }
+            '{' | '}' | ';' => tokens.push(c.to_string()),
+            _ => {}
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// Returns the source lines with comments removed and string literals emptied.
+fn strip_comments_and_strings(text: &str, dialect: Dialect) -> Vec<String> {
+    let (block_open, block_close) = match dialect {
+        Dialect::FSharp => ("(*", "*)"),
+        _ => ("/*", "*/"),
+    };
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    let mut in_string: Option<char> = None;
+    for raw in text.lines() {
+        let mut line = String::new();
+        let mut i = 0;
+        let chars: Vec<char> = raw.chars().collect();
+        while i < chars.len() {
+            let rest: String = chars[i..].iter().take(2).collect();
+            if in_block {
+                if rest == block_close {
+                    in_block = false;
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+            if let Some(quote) = in_string {
+                if chars[i] == '\\' && quote != '`' {
+                    i += 2;
+                    continue;
+                }
+                if chars[i] == quote {
+                    in_string = None;
+                    line.push(quote);
+                }
+                i += 1;
+                continue;
+            }
+            if rest == "//" {
+                break;
+            }
+            if rest == block_open {
+                in_block = true;
+                i += 2;
+                continue;
+            }
+            let c = chars[i];
+            let is_quote = c == '"' || (c == '`' && dialect == Dialect::Go);
+            // F# uses `'` for generic parameters and identifiers such as `x'` so only C-like chars count.
+            let is_char = c == '\'' && dialect != Dialect::FSharp;
+            if is_quote || is_char {
+                in_string = Some(c);
+            }
+            line.push(c);
+            i += 1;
+        }
+        // Only Go raw strings may span lines; anything else left open is a parse we got wrong.
+        if in_string.is_some() && in_string != Some('`') {
+            in_string = None;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heuristic::{measure, Dialect};
+
+    #[test]
+    fn comments_and_blank_lines_are_not_counted() {
+        let source = b"// header\n\npackage main\n/* block\n if x {\n*/\nfunc main() {}\n";
+        let metrics = measure(source, Dialect::Go);
+        assert_eq!(metrics.loc, 2);
+        assert_eq!(metrics.cyclomatic, 1);
+        assert_eq!(metrics.cognitive, 0);
+    }
+
+    #[test]
+    fn keywords_in_strings_are_ignored() {
+        let source = b"class A {\n  string s = \"if (x) { while }\";\n}\n";
+        let metrics = measure(source, Dialect::CSharp);
+        assert_eq!(metrics.cyclomatic, 1);
+        assert_eq!(metrics.cognitive, 0);
+    }
+
+    #[test]
+    fn nested_control_flow_increases_cognitive() {
+        let source = b"class A {\n  int f(int x) {\n    if (x > 1 && x < 5) {\n      for (int i = 0; i < x; i++) {\n        x += i;\n      }\n    } else {\n      x = 0;\n    }\n    return x;\n  }\n}\n";
+        let metrics = measure(source, Dialect::Java);
+        assert_eq!(metrics.loc, 12);
+        // if, for, &&
+        assert_eq!(metrics.cyclomatic, 4);
+        // if (1) + && (1) + for nested once (2) + else (1)
+        assert_eq!(metrics.cognitive, 5);
+    }
+
+    #[test]
+    fn fsharp_match_arms_and_indentation() {
+        let source = b"let f x =\n    match x with\n    | 0 -> \"zero\"\n    | _ ->\n        if x > 0 then \"pos\" else \"neg\"\n";
+        let metrics = measure(source, Dialect::FSharp);
+        // 2 arms + if
+        assert_eq!(metrics.cyclomatic, 4);
+        // match (1) + if nested once (2) + else (1)
+        assert_eq!(metrics.cognitive, 4);
+    }
+}
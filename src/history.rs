@@ -0,0 +1,467 @@
+//! A single pass over the git history shared by every git based command.
+//!
+//! [`walk`] yields one [`CommitRecord`] per commit, newest first, so a command is a fold over the records
+//! rather than its own revwalk and diff loop. A [`HistoryFilter`] narrows the stream to a time window
+//! and/or set of authors. With a [`CommitCache`] commits walked on a previous run are not diffed again.
+//! Submodules can be walked too, after the repository itself, with their paths prefixed.
+
+use crate::cache::CommitCache;
+use crate::error::HotspotError;
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::{Delta, Diff, Oid, Patch, Repository, Revwalk, Sort};
+use std::path::Path;
+
+/// What changed in a single commit.
+#[derive(Debug, Clone)]
+pub struct CommitRecord {
+    pub oid: Oid,
+    pub author: ContributorKey,
+    /// Seconds since the unix epoch.
+    pub time: i64,
+    /// The files changed compared to the first parent. Always empty for merge commits.
+    pub deltas: Vec<FileDelta>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileDelta {
+    /// The path as stored by git, which is not necessarily valid UTF-8.
+    pub path: RawPath,
+    pub status: Delta,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Restricts which commits are yielded. An empty filter lets everything through.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Only commits at or after this unix time.
+    pub since: Option<i64>,
+    /// Only commits before this unix time.
+    pub until: Option<i64>,
+    /// Only commits by one of these authors, matched case-insensitively on name or email.
+    pub authors: Vec<String>,
+}
+
+impl HistoryFilter {
+    pub fn includes(&self, time: i64, name: &str, email: &str) -> bool {
+        let after_since = self.since.is_none_or(|since| time >= since);
+        let before_until = self.until.is_none_or(|until| time < until);
+        let by_author = self.authors.is_empty()
+            || self
+                .authors
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(name) || a.eq_ignore_ascii_case(email));
+        after_since && before_until && by_author
+    }
+}
+
+pub struct History<'repo> {
+    walks: Vec<RevisionWalk<'repo>>,
+    current: usize,
+    filter: HistoryFilter,
+    cache: Option<&'repo CommitCache>,
+    scope: Option<RawPath>,
+}
+
+/// The revwalk of a single repository, the superproject or one of its submodules.
+struct RevisionWalk<'repo> {
+    repo: &'repo Repository,
+    revwalk: Revwalk<'repo>,
+    /// Where a submodule is checked out, prepended to the paths of its deltas.
+    prefix: Option<RawPath>,
+}
+
+/// A checked out submodule and where it lives relative to the root of the superproject.
+pub struct Submodule {
+    pub path: RawPath,
+    pub repo: Repository,
+}
+
+/// Opens the repository containing `path`, which may be any directory inside it.
+pub fn open_repository(path: &Path) -> Result<Repository, HotspotError> {
+    Repository::discover(path).map_err(|source| HotspotError::NotARepository {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// The directory of `path` relative to the root of `repo`, `None` when it is the root itself or
+/// the repository has no working directory.
+pub fn scope_of(repo: &Repository, path: &Path) -> Option<RawPath> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    let relative = path.strip_prefix(workdir).ok()?;
+    if relative.as_os_str().is_empty() {
+        None
+    } else {
+        Some(RawPath::from_path(relative))
+    }
+}
+
+/// Opens the submodules of `repo`, and theirs, that are checked out. Bare repositories have none.
+pub fn open_submodules(repo: &Repository) -> Result<Vec<Submodule>, HotspotError> {
+    if repo.is_bare() {
+        return Ok(vec![]);
+    }
+    let mut opened = vec![];
+    for submodule in repo.submodules()? {
+        // A submodule that has not been cloned has no history to read.
+        let sub_repo = match submodule.open() {
+            Ok(sub_repo) => sub_repo,
+            Err(_) => continue,
+        };
+        let path = RawPath::from_path(submodule.path());
+        for nested in open_submodules(&sub_repo)? {
+            opened.push(Submodule {
+                path: path.join(&nested.path),
+                repo: nested.repo,
+            });
+        }
+        opened.push(Submodule {
+            path,
+            repo: sub_repo,
+        });
+    }
+    Ok(opened)
+}
+
+fn revision_walk<'repo>(
+    repo: &'repo Repository,
+    revision: &str,
+    prefix: Option<RawPath>,
+) -> Result<RevisionWalk<'repo>, HotspotError> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .and_then(|commit| revwalk.push(commit.id()))
+        .map_err(|source| HotspotError::BadRevision {
+            revision: match &prefix {
+                Some(prefix) => format!("{} of submodule {}", revision, prefix),
+                None => revision.to_string(),
+            },
+            source,
+        })?;
+    Ok(RevisionWalk {
+        repo,
+        revwalk,
+        prefix,
+    })
+}
+
+/// Walks the history reachable from `HEAD`.
+pub fn walk<'repo>(
+    repo: &'repo Repository,
+    filter: &HistoryFilter,
+) -> Result<History<'repo>, HotspotError> {
+    walk_from(repo, "HEAD", filter)
+}
+
+/// Walks the history reachable from `revision`, which can be anything git understands (eg. a tag).
+pub fn walk_from<'repo>(
+    repo: &'repo Repository,
+    revision: &str,
+    filter: &HistoryFilter,
+) -> Result<History<'repo>, HotspotError> {
+    Ok(History {
+        walks: vec![revision_walk(repo, revision, None)?],
+        current: 0,
+        filter: filter.clone(),
+        cache: None,
+        scope: None,
+    })
+}
+
+impl<'repo> History<'repo> {
+    /// Reads commits from `cache` when present and adds the ones it is missing.
+    /// Call [`CommitCache::save`] once done to persist them.
+    pub fn with_cache(mut self, cache: Option<&'repo CommitCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Continues with the history of each of `submodules` once the repository itself is done, with
+    /// their paths prefixed by where they are checked out.
+    pub fn with_submodules(mut self, submodules: &'repo [Submodule]) -> Result<Self, HotspotError> {
+        for submodule in submodules {
+            let walk = revision_walk(&submodule.repo, "HEAD", Some(submodule.path.clone()))?;
+            self.walks.push(walk);
+        }
+        Ok(self)
+    }
+
+    /// Only keeps the deltas under the directory `scope` and skips commits left without any.
+    pub fn within(mut self, scope: Option<RawPath>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    fn placed(&self, prefix: Option<&RawPath>, mut record: CommitRecord) -> Option<CommitRecord> {
+        if let Some(prefix) = prefix {
+            for delta in record.deltas.iter_mut() {
+                delta.path = prefix.join(&delta.path);
+            }
+        }
+        match &self.scope {
+            Some(scope) => {
+                record.deltas.retain(|d| d.path.is_within(scope));
+                if record.deltas.is_empty() {
+                    None
+                } else {
+                    Some(record)
+                }
+            }
+            None => Some(record),
+        }
+    }
+
+    fn record(&self, repo: &Repository, oid: Oid) -> Result<Option<CommitRecord>, git2::Error> {
+        if let Some(cached) = self.cache.and_then(|c| c.get(&oid)) {
+            let author = &cached.author;
+            let included = self
+                .filter
+                .includes(cached.time, author.name(), author.email());
+            return Ok(if included { Some(cached) } else { None });
+        }
+
+        let commit = repo.find_commit(oid)?;
+        let author = commit.author();
+        // Names are not guaranteed to be UTF-8 (eg. Latin-1 in repositories imported from SVN).
+        let author = ContributorKey::from_bytes(author.email_bytes(), author.name_bytes());
+        let time = commit.time().seconds();
+        let included = self.filter.includes(time, author.name(), author.email());
+        // Excluded commits are still diffed when caching so a later run with another filter can use them.
+        if !included && self.cache.is_none() {
+            return Ok(None);
+        }
+
+        let deltas = match commit.parent_count() {
+            0 => {
+                let tree = commit.tree()?;
+                let diff = repo.diff_tree_to_tree(None, Some(&tree), None)?;
+                file_deltas(&diff)?
+            }
+            1 => {
+                let tree = commit.tree()?;
+                let prev_tree = commit.parent(0)?.tree()?;
+                let diff = repo.diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)?;
+                file_deltas(&diff)?
+            }
+            _ => vec![],
+        };
+
+        let record = CommitRecord {
+            oid,
+            author,
+            time,
+            deltas,
+        };
+        if let Some(cache) = self.cache {
+            cache.insert(&record);
+        }
+        Ok(if included { Some(record) } else { None })
+    }
+}
+
+impl<'repo> Iterator for History<'repo> {
+    type Item = Result<CommitRecord, HotspotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let walk = self.walks.get_mut(self.current)?;
+            let oid = match walk.revwalk.next() {
+                Some(Ok(oid)) => oid,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.current += 1;
+                    continue;
+                }
+            };
+            let walk = &self.walks[self.current];
+            match self.record(walk.repo, oid) {
+                Ok(Some(record)) => match self.placed(walk.prefix.as_ref(), record) {
+                    Some(record) => return Some(Ok(record)),
+                    None => continue,
+                },
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+fn file_deltas(diff: &Diff) -> Result<Vec<FileDelta>, git2::Error> {
+    let mut deltas = vec![];
+    for (idx, delta) in diff.deltas().enumerate() {
+        let path = match delta
+            .new_file()
+            .path_bytes()
+            .or_else(|| delta.old_file().path_bytes())
+        {
+            Some(path) => RawPath::from_bytes(path),
+            None => continue,
+        };
+        let (additions, deletions) = match Patch::from_diff(diff, idx)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats()?;
+                (additions, deletions)
+            }
+            None => (0, 0),
+        };
+        deltas.push(FileDelta {
+            path,
+            status: delta.status(),
+            additions,
+            deletions,
+        });
+    }
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::HotspotError;
+    use crate::history::{open_repository, open_submodules, scope_of, walk, HistoryFilter};
+    use crate::shared_types::RawPath;
+    use crate::test_support::{commit, init_repo, temp_dir};
+    use git2::{ObjectType, Repository, Signature, Time};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn test_repo(name: &str) -> (PathBuf, Repository) {
+        let (dir, repo) = init_repo(name);
+        commit(&repo, "a.rs", "fn a() {}\n", "Alice", 1_000);
+        commit(&repo, "a.rs", "fn a() {}\nfn b() {}\n", "Bob", 2_000);
+        commit(&repo, "b.rs", "fn c() {}\n", "Alice", 3_000);
+        (dir, repo)
+    }
+
+    #[test]
+    fn records_are_newest_first_with_line_stats() {
+        let (dir, repo) = test_repo("records");
+        let records: Vec<_> = walk(&repo, &HistoryFilter::default())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].time, 3_000);
+        assert_eq!(records[1].deltas[0].path, "a.rs");
+        assert_eq!(records[1].deltas[0].additions, 1);
+        assert_eq!(records[1].deltas[0].deletions, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn filter_limits_time_window_and_authors() {
+        let (dir, repo) = test_repo("filter");
+        let filter = HistoryFilter {
+            since: Some(1_500),
+            until: None,
+            authors: vec!["alice@example.com".to_string()],
+        };
+        let times: Vec<i64> = walk(&repo, &filter)
+            .unwrap()
+            .map(|r| r.unwrap().time)
+            .collect();
+        assert_eq!(times, vec![3_000]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn walking_within_a_directory_only_keeps_changes_under_it() {
+        let (dir, repo) = test_repo("scope");
+        commit(&repo, "src/c.rs", "fn c() {}\n", "Bob", 4_000);
+        let sub = dir.join("src");
+        let repo = open_repository(&sub).unwrap();
+        let scope = scope_of(&repo, &sub);
+        assert_eq!(scope, Some(RawPath::from("src")));
+        assert_eq!(scope_of(&repo, &dir), None);
+
+        let records: Vec<_> = walk(&repo, &HistoryFilter::default())
+            .unwrap()
+            .within(scope)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].deltas[0].path, "src/c.rs");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn submodule_history_follows_with_prefixed_paths() {
+        let (sub_dir, _) = test_repo("submodule-lib");
+        let (dir, repo) = test_repo("submodule-app");
+        let url = sub_dir.to_str().unwrap();
+        let mut submodule = repo.submodule(url, Path::new("lib"), true).unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let sig = Signature::new("Carol", "carol@example.com", &Time::new(4_000, 0)).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "add lib", &tree, &[&parent])
+            .unwrap();
+
+        let submodules = open_submodules(&repo).unwrap();
+        assert_eq!(submodules.len(), 1);
+        assert_eq!(submodules[0].path, "lib");
+        let paths: Vec<_> = walk(&repo, &HistoryFilter::default())
+            .unwrap()
+            .with_submodules(&submodules)
+            .unwrap()
+            .flat_map(|r| r.unwrap().deltas)
+            .map(|d| d.path.to_string())
+            .collect();
+        assert_eq!(paths.len(), 8);
+        assert!(paths.contains(&"lib/b.rs".to_string()));
+        assert!(paths.contains(&"lib".to_string()));
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(sub_dir).unwrap();
+    }
+
+    #[test]
+    fn non_utf8_paths_and_names_are_kept_lossily() {
+        let (dir, repo) = init_repo("lossy");
+        let blob = repo.blob(b"fn a() {}\n").unwrap();
+        let mut tree = repo.treebuilder(None).unwrap();
+        tree.insert(b"caf\xe9.rs".to_vec(), blob, 0o100644).unwrap();
+        let tree = tree.write().unwrap();
+        // Written by hand as `Signature` only accepts UTF-8 names.
+        let mut raw = format!("tree {}\nauthor ", tree).into_bytes();
+        raw.extend_from_slice(b"Jos\xe9 <jose@example.com> 1000 +0000\n");
+        raw.extend_from_slice(b"committer Jos\xe9 <jose@example.com> 1000 +0000\n\nimport\n");
+        let oid = repo.odb().unwrap().write(ObjectType::Commit, &raw).unwrap();
+        repo.reference("refs/heads/master", oid, true, "import")
+            .unwrap();
+        repo.set_head("refs/heads/master").unwrap();
+
+        let records: Vec<_> = walk(&repo, &HistoryFilter::default())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].author.is_lossy());
+        assert_eq!(records[0].author.email(), "jose@example.com");
+        assert_eq!(records[0].deltas[0].path.as_bytes(), b"caf\xe9.rs");
+        assert!(records[0].deltas[0].path.is_lossy());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn opening_a_directory_outside_a_repository_fails() {
+        let dir = temp_dir("norepo");
+        fs::create_dir_all(&dir).unwrap();
+        let result = open_repository(&dir);
+        assert!(matches!(result, Err(HotspotError::NotARepository { .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn walking_an_empty_repository_is_a_bad_revision() {
+        let (dir, repo) = init_repo("empty");
+        let result = walk(&repo, &HistoryFilter::default());
+        assert!(matches!(result, Err(HotspotError::BadRevision { .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
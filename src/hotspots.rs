@@ -0,0 +1,86 @@
+//! Ranks files by how complex they are and how often they change.
+//!
+//! A file's hotspot score is its cognitive complexity multiplied by the number of commits that touched
+//! it. Complex code nobody changes and simple code that changes a lot both score low; the files worth
+//! looking at first are the ones that are both.
+
+use crate::activity::Activity;
+use crate::shared_types::{RawPath, SpecificMetrics};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotspot {
+    pub path: RawPath,
+    pub touches: u32,
+    pub cognitive: i64,
+    pub loc: i64,
+    pub score: i64,
+}
+
+/// Scores every analyzed file in `metrics`, highest score first. The files of `activity` are keyed
+/// by paths relative to the root of the repository, so `metrics` should be too (eg. from
+/// [`crate::snapshot`]).
+pub fn rank(metrics: &[SpecificMetrics], activity: &Activity) -> Vec<Hotspot> {
+    let mut hotspots: Vec<Hotspot> = metrics
+        .iter()
+        .filter_map(|m| {
+            let cognitive = m.cognitive?;
+            let touches = activity.files.get(&m.path).map_or(0, |f| f.touches);
+            Some(Hotspot {
+                path: m.path.clone(),
+                touches,
+                cognitive,
+                loc: m.loc.unwrap_or(0),
+                score: cognitive * touches as i64,
+            })
+        })
+        .collect();
+    hotspots.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    hotspots
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::analyzers::FileMetrics;
+    use crate::hotspots::rank;
+    use crate::shared_types::{RawPath, SpecificMetrics};
+    use crate::test_support::record;
+
+    fn file(path: &str, cognitive: i64) -> SpecificMetrics {
+        let metrics = FileMetrics {
+            loc: 10,
+            cognitive,
+            cyclomatic: 1,
+            functions: vec![],
+        };
+        SpecificMetrics::analyzed(RawPath::from(path), "test", metrics)
+    }
+
+    #[test]
+    fn score_is_complexity_times_touches() {
+        let metrics = vec![
+            file("complex.rs", 20),
+            file("busy.rs", 2),
+            file("both.rs", 10),
+            SpecificMetrics::unanalyzed(RawPath::from("notes.rs")),
+        ];
+        let mut activity = Activity::default();
+        for (path, touches) in [("complex.rs", 1), ("busy.rs", 30), ("both.rs", 8)] {
+            for time in 0..touches {
+                activity.add(record("alice", time, &[path]));
+            }
+        }
+        let ranked: Vec<(String, i64)> = rank(&metrics, &activity)
+            .into_iter()
+            .map(|h| (h.path.to_string(), h.score))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("both.rs".to_string(), 80),
+                ("busy.rs".to_string(), 60),
+                ("complex.rs".to_string(), 20)
+            ]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Renders the metrics, hottest, bus factor and contributors reports as one self-contained HTML page.
+//!
+//! The page has no external dependencies so it can be opened offline or published as a CI artifact.
+//! Each table can be sorted by clicking a column header and filtered with the box above it. Files link
+//! to their row in the bus factor table, which links to the people who changed them, whose rows in the
+//! contributors table link back to the files.
+
+use crate::activity::Activity;
+use crate::shared_types::{language_name, ContributorKey, RawPath, SpecificMetrics};
+use crate::table::count;
+use chrono::DateTime;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+dl.summary { display: grid; grid-template-columns: max-content auto; gap: 0.2em 1em; }
+dl.summary dt { font-weight: bold; }
+table.report { border-collapse: collapse; margin: 0.5em 0 2em; width: 100%; }
+table.report th, table.report td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; vertical-align: top; }
+table.report th { background: #f0f0f0; cursor: pointer; user-select: none; }
+table.report th[data-order="asc"]::after { content: " \25B2"; }
+table.report th[data-order="desc"]::after { content: " \25BC"; }
+table.report td.number { text-align: right; }
+table.report tr:target { background: #fff3c4; }
+input.filter { padding: 0.25em; width: 20em; }
+"#;
+
+const SCRIPT: &str = r#"
+function sortKey(cell) {
+  var value = cell.getAttribute('data-sort');
+  return value !== null ? Number(value) : cell.textContent.toLowerCase();
+}
+document.querySelectorAll('table.report').forEach(function (table) {
+  var body = table.tBodies[0];
+  table.querySelectorAll('th').forEach(function (th, column) {
+    th.addEventListener('click', function () {
+      var descending = th.getAttribute('data-order') !== 'desc';
+      table.querySelectorAll('th').forEach(function (other) { other.removeAttribute('data-order'); });
+      th.setAttribute('data-order', descending ? 'desc' : 'asc');
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var x = sortKey(a.cells[column]), y = sortKey(b.cells[column]);
+        var order = x < y ? -1 : x > y ? 1 : 0;
+        return descending ? -order : order;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+document.querySelectorAll('input.filter').forEach(function (input) {
+  var body = document.getElementById(input.getAttribute('data-table')).tBodies[0];
+  input.addEventListener('input', function () {
+    var text = input.value.toLowerCase();
+    Array.prototype.forEach.call(body.rows, function (row) {
+      row.hidden = text !== '' && row.textContent.toLowerCase().indexOf(text) < 0;
+    });
+  });
+});
+function reveal() {
+  var row = document.getElementById(decodeURIComponent(location.hash.slice(1)));
+  if (row) { row.hidden = false; }
+}
+window.addEventListener('hashchange', reveal);
+reveal();
+"#;
+
+/// What the report is of, shown in its header.
+pub struct ReportHeader {
+    /// The repository directory.
+    pub repository: String,
+    /// The revision as given, eg. `HEAD` or a branch name.
+    pub revision: String,
+    /// The id of the commit the revision resolved to.
+    pub commit: String,
+}
+
+/// Escapes `text` for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn date(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|t| t.date_naive().to_string())
+        .unwrap_or_default()
+}
+
+fn number(value: impl ToString) -> String {
+    let value = value.to_string();
+    format!(r#"<td class="number" data-sort="{0}">{0}</td>"#, value)
+}
+
+fn header_row(columns: &[&str]) -> String {
+    let cells: String = columns
+        .iter()
+        .map(|c| format!("<th>{}</th>", escape(c)))
+        .collect();
+    format!("<thead><tr>{}</tr></thead>", cells)
+}
+
+/// Anchors for the files and contributors, so rows in one table can link to those in another.
+struct Anchors {
+    files: HashMap<RawPath, String>,
+    contributors: HashMap<ContributorKey, String>,
+}
+
+impl Anchors {
+    fn file(&self, path: &RawPath) -> String {
+        match self.files.get(path) {
+            Some(id) => format!(r##"<a href="#{}">{}</a>"##, id, escape(&path.to_string())),
+            None => escape(&path.to_string()),
+        }
+    }
+
+    fn contributor(&self, key: &ContributorKey) -> String {
+        match self.contributors.get(key) {
+            Some(id) => format!(r##"<a href="#{}">{}</a>"##, id, escape(&key.to_string())),
+            None => escape(&key.to_string()),
+        }
+    }
+}
+
+/// The whole page. `metrics` should have paths relative to the repository root, like the history in
+/// `activity`, for files to be linked to who changed them.
+pub fn render(header: &ReportHeader, metrics: &[SpecificMetrics], activity: &Activity) -> String {
+    let mut paths: Vec<&RawPath> = activity.files.keys().collect();
+    paths.sort();
+    let mut contributors: Vec<(&ContributorKey, _)> = activity.contributors.iter().collect();
+    contributors.sort_by(|a, b| {
+        b.1.commits
+            .cmp(&a.1.commits)
+            .then(a.0.email().cmp(b.0.email()))
+    });
+    let anchors = Anchors {
+        files: paths
+            .iter()
+            .enumerate()
+            .map(|(i, p)| ((*p).clone(), format!("file-{}", i)))
+            .collect(),
+        contributors: contributors
+            .iter()
+            .enumerate()
+            .map(|(i, (k, _))| ((*k).clone(), format!("contributor-{}", i)))
+            .collect(),
+    };
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Hotspot report: {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&header.repository),
+        STYLE
+    );
+    summary(&mut html, header, metrics, activity);
+    metrics_table(&mut html, metrics, &anchors);
+    hottest_table(&mut html, &paths, activity, &anchors);
+    busfactor_table(&mut html, &paths, activity, &anchors);
+    contributors_table(&mut html, &contributors, &anchors);
+    let _ = write!(html, "<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    html
+}
+
+fn summary(
+    html: &mut String,
+    header: &ReportHeader,
+    metrics: &[SpecificMetrics],
+    activity: &Activity,
+) {
+    let mut languages: BTreeMap<&str, usize> = BTreeMap::new();
+    for m in metrics {
+        let extension = m.path.to_path_buf();
+        let extension = extension
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if let Some(language) = language_name(extension) {
+            *languages.entry(language).or_insert(0) += 1;
+        }
+    }
+    let mut languages: Vec<(&str, usize)> = languages.into_iter().collect();
+    languages.sort_by_key(|(_, files)| std::cmp::Reverse(*files));
+    let languages: Vec<String> = languages
+        .iter()
+        .map(|(l, n)| format!("{} ({})", l, count(*n, "file")))
+        .collect();
+    let range = match (activity.first_commit_at, activity.last_commit_at) {
+        (Some(first), Some(last)) => format!("{} to {}", date(first), date(last)),
+        _ => "-".to_string(),
+    };
+    let _ = write!(
+        html,
+        "<h1>Hotspot report</h1>\n<dl class=\"summary\">\n<dt>Repository</dt><dd>{}</dd>\n<dt>Revision</dt><dd>{} ({})</dd>\n<dt>Commits</dt><dd>{}</dd>\n<dt>Date range</dt><dd>{}</dd>\n<dt>Languages</dt><dd>{}</dd>\n</dl>\n",
+        escape(&header.repository),
+        escape(&header.revision),
+        escape(&header.commit),
+        activity.commits,
+        range,
+        escape(&languages.join(", ")),
+    );
+}
+
+fn table_start(html: &mut String, id: &str, title: &str, columns: &[&str]) {
+    let _ = write!(
+        html,
+        "<h2>{1}</h2>\n<input class=\"filter\" type=\"search\" placeholder=\"Filter {2}\" data-table=\"{0}\">\n<table class=\"report\" id=\"{0}\">\n{3}\n<tbody>\n",
+        id,
+        title,
+        title.to_lowercase(),
+        header_row(columns)
+    );
+}
+
+fn table_end(html: &mut String) {
+    html.push_str("</tbody>\n</table>\n");
+}
+
+fn metrics_table(html: &mut String, metrics: &[SpecificMetrics], anchors: &Anchors) {
+    table_start(
+        html,
+        "metrics",
+        "Metrics",
+        &["File", "Lines", "Cognitive", "Cyclomatic", "Analyzer"],
+    );
+    for m in metrics {
+        if let (Some(loc), Some(cognitive), Some(cyclomatic)) = (m.loc, m.cognitive, m.cyclomatic) {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td>{}{}{}<td>{}</td></tr>",
+                anchors.file(&m.path),
+                number(loc),
+                number(cognitive),
+                number(cyclomatic),
+                escape(m.analyzer.as_deref().unwrap_or_default())
+            );
+        }
+    }
+    table_end(html);
+}
+
+fn hottest_table(html: &mut String, paths: &[&RawPath], activity: &Activity, anchors: &Anchors) {
+    let mut paths = paths.to_vec();
+    paths.sort_by_key(|p| std::cmp::Reverse(activity.files[*p].touches));
+    table_start(
+        html,
+        "hottest",
+        "Hottest",
+        &["File", "Changes", "Last changed by", "Last changed at"],
+    );
+    for path in paths {
+        let file = &activity.files[path];
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td>{}<td>{}</td><td data-sort=\"{}\">{}</td></tr>",
+            anchors.file(path),
+            number(file.touches),
+            anchors.contributor(&file.last_changed_by),
+            file.last_changed_at,
+            date(file.last_changed_at)
+        );
+    }
+    table_end(html);
+}
+
+fn busfactor_table(html: &mut String, paths: &[&RawPath], activity: &Activity, anchors: &Anchors) {
+    let mut paths = paths.to_vec();
+    paths.sort_by_key(|p| activity.files[*p].contributors.len());
+    table_start(
+        html,
+        "busfactor",
+        "Bus factor",
+        &["File", "Bus factor", "Contributors"],
+    );
+    for path in paths {
+        let file = &activity.files[path];
+        let mut people: Vec<(&ContributorKey, &u32)> = file.contributors.iter().collect();
+        people.sort_by(|a, b| b.1.cmp(a.1).then(a.0.email().cmp(b.0.email())));
+        let people: Vec<String> = people
+            .iter()
+            .map(|(k, commits)| format!("{} ({})", anchors.contributor(k), commits))
+            .collect();
+        let _ = writeln!(
+            html,
+            "<tr id=\"{}\"><td>{}</td>{}<td>{}</td></tr>",
+            anchors.files[path],
+            escape(&path.to_string()),
+            number(file.contributors.len()),
+            people.join(", ")
+        );
+    }
+    table_end(html);
+}
+
+fn contributors_table(
+    html: &mut String,
+    contributors: &[(&ContributorKey, &crate::activity::ContributorActivity)],
+    anchors: &Anchors,
+) {
+    table_start(
+        html,
+        "contributors",
+        "Contributors",
+        &[
+            "Contributor",
+            "Commits",
+            "Files touched",
+            "Lines added",
+            "Lines removed",
+            "First commit",
+            "Last commit",
+            "Files",
+        ],
+    );
+    for (key, contributor) in contributors {
+        let mut files: Vec<&RawPath> = contributor.files.iter().collect();
+        files.sort();
+        let links: Vec<String> = files.iter().map(|f| anchors.file(f)).collect();
+        let _ = writeln!(
+            html,
+            "<tr id=\"{}\"><td>{}</td>{}{}{}{}<td data-sort=\"{}\">{}</td><td data-sort=\"{}\">{}</td><td><details><summary>{}</summary>{}</details></td></tr>",
+            anchors.contributors[*key],
+            escape(&key.to_string()),
+            number(contributor.commits),
+            number(contributor.files.len()),
+            number(contributor.additions),
+            number(contributor.deletions),
+            contributor.first_commit_at,
+            date(contributor.first_commit_at),
+            contributor.last_commit_at,
+            date(contributor.last_commit_at),
+            count(files.len(), "file"),
+            links.join("<br>")
+        );
+    }
+    table_end(html);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::analyzers::FileMetrics;
+    use crate::history::{CommitRecord, FileDelta};
+    use crate::html::{render, ReportHeader};
+    use crate::shared_types::{ContributorKey, RawPath, SpecificMetrics};
+    use git2::{Delta, Oid};
+
+    #[test]
+    fn files_and_contributors_link_to_each_other() {
+        let mut activity = Activity::default();
+        activity.add(CommitRecord {
+            oid: Oid::zero(),
+            author: ContributorKey::new("ann@example.com".to_string(), "Ann <dev>".to_string()),
+            time: 1_600_000_000,
+            deltas: vec![FileDelta {
+                path: RawPath::from("src/a&b.rs"),
+                status: Delta::Added,
+                additions: 10,
+                deletions: 0,
+            }],
+        });
+        let metrics = vec![SpecificMetrics::analyzed(
+            RawPath::from("src/a&b.rs"),
+            "test",
+            FileMetrics {
+                loc: 10,
+                cognitive: 3,
+                cyclomatic: 2,
+                functions: vec![],
+            },
+        )];
+        let header = ReportHeader {
+            repository: "/work/repo".to_string(),
+            revision: "HEAD".to_string(),
+            commit: "abc123".to_string(),
+        };
+        let html = render(&header, &metrics, &activity);
+        assert!(html.contains("<dt>Languages</dt><dd>Rust (1 file)</dd>"));
+        assert!(html.contains("<summary>1 file</summary>"));
+        assert!(html.contains("<dt>Date range</dt><dd>2020-09-13 to 2020-09-13</dd>"));
+        assert!(html.contains(r##"<a href="#file-0">src/a&amp;b.rs</a>"##));
+        assert!(html.contains(r#"<tr id="file-0">"#));
+        assert!(html
+            .contains(r##"<a href="#contributor-0">Ann &lt;dev&gt;&lt;ann@example.com&gt;</a>"##));
+        assert!(html.contains(r#"<tr id="contributor-0">"#));
+        assert!(!html.contains("http"));
+    }
+}
@@ -0,0 +1,252 @@
+//! Finds the files whose history mostly belongs to people who have left, so the knowledge that
+//! went with them can be rebuilt where it matters most: in complex files that change often.
+
+use crate::activity::Activity;
+use crate::error::HotspotError;
+use crate::shared_types::{ContributorKey, RawPath, SpecificMetrics};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+
+/// Who counts as having left.
+#[derive(Debug, Clone)]
+pub struct Departed {
+    people: GlobSet,
+    /// Anyone whose last commit is before this time, in seconds since the unix epoch.
+    inactive_since: Option<i64>,
+}
+
+impl Departed {
+    /// The people whose email or name matches one of `people`, ignoring case, and anyone who has not
+    /// committed since `inactive_since`. People can be globs, eg. `*@contractor.example.com`.
+    pub fn new(people: &[String], inactive_since: Option<i64>) -> Result<Self, HotspotError> {
+        let mut builder = GlobSetBuilder::new();
+        for person in people {
+            let glob = GlobBuilder::new(person)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| {
+                    HotspotError::InvalidConfig(format!("invalid person '{}': {}", person, e))
+                })?;
+            builder.add(glob);
+        }
+        let people = builder
+            .build()
+            .map_err(|e| HotspotError::InvalidConfig(e.to_string()))?;
+        Ok(Departed {
+            people,
+            inactive_since,
+        })
+    }
+
+    /// Everyone in `activity` who has left.
+    pub fn who(&self, activity: &Activity) -> HashSet<ContributorKey> {
+        activity
+            .contributors
+            .iter()
+            .filter(|(c, a)| {
+                self.people.is_match(c.email())
+                    || self.people.is_match(c.name())
+                    || self.inactive_since.is_some_and(|t| a.last_commit_at < t)
+            })
+            .map(|(c, _)| c.clone())
+            .collect()
+    }
+}
+
+/// How much of a file's history belongs to people who have left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnowledgeLoss {
+    pub path: RawPath,
+    /// Commits that changed the file.
+    pub touches: u32,
+    /// Of those, the ones made by people who have left.
+    pub departed_touches: u32,
+    pub cognitive: i64,
+    pub loc: i64,
+    /// Cognitive complexity times the number of commits, as for hotspots.
+    pub score: i64,
+    /// The people who have left that changed the file, most commits first.
+    pub departed: Vec<ContributorKey>,
+}
+
+impl KnowledgeLoss {
+    /// The share of the file's commits made by people who have left, from 0 to 1.
+    pub fn share(&self) -> f64 {
+        if self.touches == 0 {
+            0.0
+        } else {
+            self.departed_touches as f64 / self.touches as f64
+        }
+    }
+
+    /// True when most of the file's commits were made by people who have left.
+    pub fn is_at_risk(&self) -> bool {
+        self.departed_touches * 2 > self.touches
+    }
+}
+
+/// How the changes to each analyzed file in `metrics` split between people who have left and those
+/// who have not, highest scoring first. `metrics` and `activity` are keyed by paths relative to the
+/// root of the repository.
+pub fn assess(
+    metrics: &[SpecificMetrics],
+    activity: &Activity,
+    departed: &HashSet<ContributorKey>,
+) -> Vec<KnowledgeLoss> {
+    let mut files: Vec<KnowledgeLoss> = metrics
+        .iter()
+        .filter_map(|m| {
+            let cognitive = m.cognitive?;
+            let file = activity.files.get(&m.path)?;
+            let mut people: Vec<(&ContributorKey, u32)> = file
+                .contributors
+                .iter()
+                .filter(|(c, _)| departed.contains(*c))
+                .map(|(c, n)| (c, *n))
+                .collect();
+            people.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.email().cmp(b.0.email())));
+            Some(KnowledgeLoss {
+                path: m.path.clone(),
+                touches: file.touches,
+                departed_touches: people.iter().map(|(_, n)| n).sum(),
+                cognitive,
+                loc: m.loc.unwrap_or(0),
+                score: cognitive * file.touches as i64,
+                departed: people.into_iter().map(|(c, _)| c.clone()).collect(),
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    files
+}
+
+/// Files rolled up into a group, and how much of them is at risk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LossTotal {
+    pub files: usize,
+    pub at_risk: usize,
+    pub touches: u32,
+    pub departed_touches: u32,
+    /// Cognitive complexity of the files at risk.
+    pub cognitive_at_risk: i64,
+}
+
+impl LossTotal {
+    pub fn add(&mut self, loss: &KnowledgeLoss) {
+        self.files += 1;
+        self.touches += loss.touches;
+        self.departed_touches += loss.departed_touches;
+        if loss.is_at_risk() {
+            self.at_risk += 1;
+            self.cognitive_at_risk += loss.cognitive;
+        }
+    }
+
+    /// The share of the group's commits made by people who have left, from 0 to 1.
+    pub fn share(&self) -> f64 {
+        if self.touches == 0 {
+            0.0
+        } else {
+            self.departed_touches as f64 / self.touches as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::analyzers::FileMetrics;
+    use crate::history::{CommitRecord, FileDelta};
+    use crate::knowledge::{assess, Departed};
+    use crate::shared_types::{ContributorKey, RawPath, SpecificMetrics};
+    use git2::{Delta, Oid};
+
+    fn record(author: &str, time: i64, paths: &[&str]) -> CommitRecord {
+        CommitRecord {
+            oid: Oid::zero(),
+            author: ContributorKey::new(format!("{}@example.com", author), author.to_string()),
+            time,
+            deltas: paths
+                .iter()
+                .map(|p| FileDelta {
+                    path: RawPath::from(*p),
+                    status: Delta::Modified,
+                    additions: 1,
+                    deletions: 0,
+                })
+                .collect(),
+        }
+    }
+
+    fn file(path: &str, cognitive: i64) -> SpecificMetrics {
+        let metrics = FileMetrics {
+            loc: 10,
+            cognitive,
+            cyclomatic: 1,
+            functions: vec![],
+        };
+        SpecificMetrics::analyzed(RawPath::from(path), "test", metrics)
+    }
+
+    fn activity() -> Activity {
+        Activity::collect(
+            vec![
+                record("alice", 1_000, &["a.rs", "b.rs"]),
+                record("alice", 2_000, &["a.rs"]),
+                record("bob", 3_000, &["a.rs", "b.rs"]),
+                record("carol", 9_000, &["b.rs", "c.rs"]),
+            ]
+            .into_iter()
+            .map(Ok),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn people_leave_by_name_or_by_going_quiet() {
+        let activity = activity();
+        let mut named: Vec<String> = Departed::new(&["ALICE".to_string()], None)
+            .unwrap()
+            .who(&activity)
+            .into_iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        named.sort();
+        assert_eq!(named, vec!["alice"]);
+        let mut quiet: Vec<String> = Departed::new(&[], Some(5_000))
+            .unwrap()
+            .who(&activity)
+            .into_iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        quiet.sort();
+        assert_eq!(quiet, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn files_mostly_changed_by_departed_people_are_at_risk() {
+        let activity = activity();
+        let departed = Departed::new(&["alice".to_string()], None)
+            .unwrap()
+            .who(&activity);
+        let losses = assess(
+            &[file("a.rs", 4), file("b.rs", 10), file("c.rs", 1)],
+            &activity,
+            &departed,
+        );
+        let ranked: Vec<(String, i64, bool)> = losses
+            .iter()
+            .map(|l| (l.path.to_string(), l.score, l.is_at_risk()))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("b.rs".to_string(), 30, false),
+                ("a.rs".to_string(), 12, true),
+                ("c.rs".to_string(), 1, false),
+            ]
+        );
+        assert!((losses[1].share() - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(losses[1].departed[0].name(), "alice");
+    }
+}
@@ -1,15 +1,65 @@
+pub mod activity;
+pub mod analyzers;
+pub mod baseline;
+pub mod cache;
+pub mod changes;
+pub mod check;
+pub mod codeowners;
+pub mod components;
+pub mod config;
+pub mod diff;
+pub mod error;
+pub mod heuristic;
+pub mod history;
+pub mod hotspots;
+pub mod html;
+pub mod knowledge;
+pub mod rollup;
+pub mod sarif;
+pub mod snapshot;
+pub mod table;
+pub mod teams;
+#[cfg(test)]
+mod test_support;
+pub mod treemap;
+pub mod trend;
+
 pub mod shared_types {
+    use crate::analyzers::{FileMetrics, FunctionMetrics};
+    use crate::changes::ChangeSet;
+    use crate::codeowners::DriftLimits;
+    use crate::history::HistoryFilter;
+    use crate::rollup::Grouping;
+    use crate::table::Terminal;
+    use crate::trend::Interval;
     use core::fmt;
 
     use globset::{Error, Glob, GlobSet, GlobSetBuilder};
     use std::fmt::Display;
     use std::hash::{Hash, Hasher};
-    pub const FILE_GLOBS: [&'static str; 4] = [
-        "*.{cs,c,cpp,fs,go,js,java,py,rs,ts,tsx}",
+    use std::path::{Path, PathBuf};
+    pub const FILE_GLOBS: [&str; 4] = [
+        "*.{cs,csx,c,cpp,fs,fsi,fsx,go,js,java,py,rs,ts,tsx}",
         "!.*",
         "!node_modules/",
         "!target/",
     ];
+    /// The language of a file with `extension`, for the extensions in [`FILE_GLOBS`].
+    pub fn language_name(extension: &str) -> Option<&'static str> {
+        match extension {
+            "c" => Some("C"),
+            "cpp" => Some("C++"),
+            "cs" | "csx" => Some("C#"),
+            "fs" | "fsi" | "fsx" => Some("F#"),
+            "go" => Some("Go"),
+            "java" => Some("Java"),
+            "js" => Some("JavaScript"),
+            "py" => Some("Python"),
+            "rs" => Some("Rust"),
+            "ts" | "tsx" => Some("TypeScript"),
+            _ => None,
+        }
+    }
     #[allow(dead_code)]
     #[derive(Debug)]
     pub enum Verbosity {
@@ -26,30 +76,40 @@ pub mod shared_types {
     }
     impl Verbosity {
         pub fn is_quiet(&self) -> bool {
-            match self {
-                Verbosity::Silent => true,
-                Verbosity::Error => true,
-                _ => false,
-            }
+            matches!(self, Verbosity::Silent | Verbosity::Error)
         }
         pub fn is_not_quiet(&self) -> bool {
             !self.is_quiet()
         }
         pub fn is_verbose(&self) -> bool {
-            match self {
-                Verbosity::Debug => true,
-                Verbosity::Trace => true,
-                _ => false,
-            }
+            matches!(self, Verbosity::Debug | Verbosity::Trace)
         }
         #[allow(dead_code)]
         pub fn is_informative(&self) -> bool {
-            match self {
-                Verbosity::Info => true,
-                Verbosity::Debug => true,
-                Verbosity::Trace => true,
-                _ => false,
-            }
+            matches!(self, Verbosity::Info | Verbosity::Debug | Verbosity::Trace)
+        }
+    }
+    /// How a command prints its report.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum OutputFormat {
+        /// Tables for reading in a terminal, with the command's progress messages.
+        Table(Terminal),
+        /// SARIF 2.1.0 JSON for code scanning tools, with nothing else on stdout.
+        Sarif,
+        /// An SVG treemap of the files, sized by lines of code and coloured by how often they changed.
+        Svg,
+        /// GitHub flavoured Markdown tables with full paths, for PR comments and wikis. Rows past
+        /// `collapse_after` are put in a collapsed `<details>` section.
+        Markdown { collapse_after: Option<usize> },
+    }
+    impl Default for OutputFormat {
+        fn default() -> Self {
+            OutputFormat::Table(Terminal::default())
+        }
+    }
+    impl OutputFormat {
+        pub fn is_table(&self) -> bool {
+            matches!(self, OutputFormat::Table(_))
         }
     }
     // TODO: A context object that is told about each step starting, ending, progress, etc.
@@ -59,6 +119,30 @@ pub mod shared_types {
         pub output: String,
         pub includes: String,
         pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        /// Measure the files committed at this revision instead of those on disk.
+        pub revision: Option<String>,
+        pub format: OutputFormat,
+        /// Only report the files this change adds or modifies.
+        pub changes: Option<ChangeSet>,
+        /// Rolls the report up into directories or components instead of listing every file.
+        pub group_by: Option<Grouping>,
+        /// The configuration given with `--config` that sets the components, and the rules for
+        /// SARIF output.
+        pub config_path: Option<String>,
+        /// How many of the highest scoring files are reported as hotspots in SARIF output.
+        pub top: u32,
+    }
+    pub enum CacheAction {
+        Clear,
+        Stats,
+    }
+    pub struct CacheConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub action: CacheAction,
     }
     pub struct ContributorsConfig {
         pub repository_path: String,
@@ -66,6 +150,15 @@ pub mod shared_types {
         pub output: String,
         pub includes: String,
         pub excludes: String,
+        pub use_cache: bool,
+        pub history: HistoryFilter,
+        /// Also walk the history of checked out submodules.
+        pub recurse_submodules: bool,
+        pub format: OutputFormat,
+        /// Reports on the teams of the configuration rather than people.
+        pub by_team: bool,
+        /// The configuration given with `--config` that sets the teams.
+        pub config_path: Option<String>,
     }
     pub struct BusFactorConfig {
         pub repository_path: String,
@@ -73,12 +166,153 @@ pub mod shared_types {
         pub output: String,
         pub includes: String,
         pub excludes: String,
+        pub use_cache: bool,
+        pub history: HistoryFilter,
+        /// Also walk the history of checked out submodules.
+        pub recurse_submodules: bool,
+        /// Only report the files this change adds or modifies.
+        pub changes: Option<ChangeSet>,
+        pub format: OutputFormat,
+        /// Rolls the report up into directories or components instead of listing every file.
+        pub group_by: Option<Grouping>,
+        /// The configuration given with `--config` that sets the components.
+        pub config_path: Option<String>,
+        /// Compares who changes each file to the owners in its `CODEOWNERS` file instead.
+        pub owners: Option<DriftLimits>,
+        /// Counts the teams of the configuration rather than people.
+        pub by_team: bool,
+    }
+    pub struct CheckConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub revision: String,
+        /// The rules file given with `--config`, `.hotspot.toml` in the repository root otherwise.
+        pub config_path: Option<String>,
+        /// Violations recorded in this file are only reported when they get worse.
+        pub baseline: Option<String>,
+        /// Records the current violations in `baseline` instead of comparing against it.
+        pub write_baseline: bool,
+        pub format: OutputFormat,
+        /// How many of the highest scoring files are reported as hotspots in SARIF output.
+        pub top: u32,
+        /// Only report the files this change adds or modifies.
+        pub changes: Option<ChangeSet>,
     }
+    pub struct ComponentsConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub revision: String,
+        pub history: HistoryFilter,
+        /// The configuration given with `--config` that sets the components.
+        pub config_path: Option<String>,
+        pub format: OutputFormat,
+    }
+    pub struct DiffConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub base: String,
+        pub head: String,
+        /// How many of the highest scoring files count as hotspots.
+        pub top: u32,
+        pub format: OutputFormat,
+    }
+    pub struct KnowledgeConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub revision: String,
+        /// Emails or names of the people who have left.
+        pub departed: Vec<String>,
+        /// Anyone who has not committed for this many months has left too.
+        pub inactive_months: Option<u32>,
+        /// Rolls the report up into directories or components instead of listing every file.
+        pub group_by: Option<Grouping>,
+        /// The configuration given with `--config` that sets the components.
+        pub config_path: Option<String>,
+        pub format: OutputFormat,
+    }
+    pub struct ReportConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub revision: String,
+        pub history: HistoryFilter,
+        /// Where the HTML report is written.
+        pub html: String,
+    }
+    pub struct TrendConfig {
+        pub repository_path: String,
+        pub verbosity: Verbosity,
+        pub output: String,
+        pub includes: String,
+        pub excludes: String,
+        pub use_cache: bool,
+        /// Number of files parsed in parallel, `0` uses one thread per CPU.
+        pub jobs: usize,
+        pub interval: Interval,
+        /// Also list every file at every sample.
+        pub per_file: bool,
+        pub format: OutputFormat,
+    }
+    #[derive(Debug, Clone)]
     pub struct SpecificMetrics {
-        pub path: String,
+        pub path: RawPath,
         pub cyclomatic: Option<i64>,
         pub cognitive: Option<i64>,
         pub loc: Option<i64>,
+        /// Which analyzer produced the numbers, `None` when the file could not be analyzed.
+        pub analyzer: Option<String>,
+        pub functions: Vec<FunctionMetrics>,
+    }
+    impl SpecificMetrics {
+        pub fn analyzed(path: RawPath, analyzer: &str, metrics: FileMetrics) -> Self {
+            SpecificMetrics {
+                path,
+                cyclomatic: Some(metrics.cyclomatic),
+                cognitive: Some(metrics.cognitive),
+                loc: Some(metrics.loc),
+                analyzer: Some(analyzer.to_string()),
+                functions: metrics.functions,
+            }
+        }
+        pub fn unanalyzed(path: RawPath) -> Self {
+            SpecificMetrics {
+                path,
+                cyclomatic: None,
+                cognitive: None,
+                loc: None,
+                analyzer: None,
+                functions: vec![],
+            }
+        }
     }
 
     pub struct HottestConfig {
@@ -87,7 +321,18 @@ pub mod shared_types {
         pub output: String,
         pub includes: String,
         pub excludes: String,
+        pub use_cache: bool,
+        pub history: HistoryFilter,
+        /// Also walk the history of checked out submodules.
+        pub recurse_submodules: bool,
         pub top: u32,
+        /// Only report the files this change adds or modifies.
+        pub changes: Option<ChangeSet>,
+        pub format: OutputFormat,
+        /// Rolls the report up into directories or components instead of listing every file.
+        pub group_by: Option<Grouping>,
+        /// The configuration given with `--config` that sets the components.
+        pub config_path: Option<String>,
     }
 
     //use chrono::{DateTime, Utc};
@@ -102,14 +347,119 @@ pub mod shared_types {
     // pub fn init(path: String) -> RepositoryInfo {
     //     unimplemented!("Nothing implemented to initialize for {}", path)
     // }
+    /// Appended to names and paths that are not valid UTF-8 and were decoded lossily for display.
+    pub const LOSSY_MARKER: &str = " (lossy)";
+
+    /// A repository path kept as the raw bytes git stores, as file names are not guaranteed to be
+    /// UTF-8 (eg. repositories imported from SVN).
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct RawPath(Vec<u8>);
+    impl RawPath {
+        pub fn from_bytes(bytes: &[u8]) -> Self {
+            RawPath(bytes.to_vec())
+        }
+        #[cfg(unix)]
+        pub fn from_path(path: &Path) -> Self {
+            use std::os::unix::ffi::OsStrExt;
+            RawPath(path.as_os_str().as_bytes().to_vec())
+        }
+        #[cfg(not(unix))]
+        pub fn from_path(path: &Path) -> Self {
+            RawPath(path.to_string_lossy().as_bytes().to_vec())
+        }
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+        /// True when the path is not valid UTF-8 and can only be shown lossily.
+        pub fn is_lossy(&self) -> bool {
+            std::str::from_utf8(&self.0).is_err()
+        }
+        /// `path` inside this directory.
+        pub fn join(&self, path: &RawPath) -> RawPath {
+            let mut joined = self.0.clone();
+            if !joined.is_empty() && !joined.ends_with(b"/") {
+                joined.push(b'/');
+            }
+            joined.extend_from_slice(&path.0);
+            RawPath(joined)
+        }
+        #[cfg(unix)]
+        pub fn to_path_buf(&self) -> PathBuf {
+            use std::os::unix::ffi::OsStrExt;
+            PathBuf::from(std::ffi::OsStr::from_bytes(&self.0))
+        }
+        #[cfg(not(unix))]
+        pub fn to_path_buf(&self) -> PathBuf {
+            PathBuf::from(self.to_string_lossy())
+        }
+        /// True when this is `dir` or a path inside it.
+        pub fn is_within(&self, dir: &RawPath) -> bool {
+            let dir = dir.0.strip_suffix(b"/").unwrap_or(&dir.0);
+            match self.0.strip_prefix(dir) {
+                Some(rest) => rest.is_empty() || rest[0] == b'/',
+                None => false,
+            }
+        }
+        /// The path with invalid sequences replaced, without the lossy marker. Use for matching.
+        pub fn to_string_lossy(&self) -> String {
+            String::from_utf8_lossy(&self.0).to_string()
+        }
+    }
+    impl From<&str> for RawPath {
+        fn from(path: &str) -> Self {
+            RawPath(path.as_bytes().to_vec())
+        }
+    }
+    impl PartialEq<&str> for RawPath {
+        fn eq(&self, other: &&str) -> bool {
+            self.0 == other.as_bytes()
+        }
+    }
+    impl Display for RawPath {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.to_string_lossy())?;
+            if self.is_lossy() {
+                write!(f, "{}", LOSSY_MARKER)?;
+            }
+            Ok(())
+        }
+    }
+
     #[derive(Debug, Clone)]
     pub struct ContributorKey {
         email: String,
         name: String,
+        lossy: bool,
     }
     impl ContributorKey {
         pub fn new(email: String, name: String) -> Self {
-            Self { email, name }
+            Self {
+                email,
+                name,
+                lossy: false,
+            }
+        }
+        /// Decodes a name and email that may not be valid UTF-8, remembering if anything was replaced.
+        pub fn from_bytes(email: &[u8], name: &[u8]) -> Self {
+            let lossy = std::str::from_utf8(email).is_err() || std::str::from_utf8(name).is_err();
+            Self {
+                email: String::from_utf8_lossy(email).to_string(),
+                name: String::from_utf8_lossy(name).to_string(),
+                lossy,
+            }
+        }
+        pub fn lossy(mut self, lossy: bool) -> Self {
+            self.lossy = lossy;
+            self
+        }
+        pub fn email(&self) -> &str {
+            &self.email
+        }
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+        pub fn is_lossy(&self) -> bool {
+            self.lossy
         }
     }
     impl Hash for ContributorKey {
@@ -125,14 +475,18 @@ pub mod shared_types {
     impl Eq for ContributorKey {}
     impl Display for ContributorKey {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-            write!(f, "{}<{}>", self.name, self.email)
+            write!(f, "{}<{}>", self.name, self.email)?;
+            if self.lossy {
+                write!(f, "{}", LOSSY_MARKER)?;
+            }
+            Ok(())
         }
     }
     pub fn truncate_right(value: String, length: usize) -> String {
         if value.len() <= length {
             value
         } else {
-            return format!("{1:.*}...", length - 3, value);
+            format!("{1:.*}...", length - 3, value)
         }
     }
     pub fn truncate_left(value: String, length: usize) -> String {
@@ -147,7 +501,7 @@ pub mod shared_types {
                 .chars()
                 .rev()
                 .collect();
-            return format!("...{1:.*}", length - 3, left_truncated);
+            format!("...{1:.*}", length - 3, left_truncated)
         }
     }
     fn build_globset(patterns: Vec<&str>) -> Result<GlobSet, Error> {
@@ -161,14 +515,21 @@ pub mod shared_types {
         let glob_set = build_globset(patterns).unwrap();
         glob_set.is_match(path)
     }
+    /// Matches the paths [`is_supported_file`] accepts for [`FILE_GLOBS`]. Build it once and reuse it
+    /// for every path, eg. across the deltas of a history.
+    pub fn supported_files() -> GlobSet {
+        build_globset(FILE_GLOBS.to_vec()).unwrap()
+    }
     #[cfg(test)]
     mod tests {
-        use crate::shared_types::{is_supported_file, FILE_GLOBS};
+        use crate::shared_types::{
+            is_supported_file, supported_files, ContributorKey, RawPath, FILE_GLOBS,
+        };
         #[test]
         fn non_matching_path_is_not_supported() {
             let path = "notamatch.xyz";
             let supported = is_supported_file(FILE_GLOBS.to_vec(), path);
-            assert_eq!(supported, false);
+            assert!(!supported);
         }
         #[test]
         fn matching_path_is_supported() {
@@ -177,10 +538,27 @@ pub mod shared_types {
             assert!(supported);
         }
         #[test]
+        fn supported_files_match_like_the_file_globs() {
+            let supported = supported_files();
+            assert!(supported.is_match("src/test.rs"));
+            assert!(!supported.is_match("notamatch.xyz"));
+        }
+        #[test]
         fn pattern_match_ext_is_supported() {
             let path = "test.rs";
             let supported = is_supported_file(["**.rs"].to_vec(), path);
             assert!(supported);
         }
+        #[test]
+        fn non_utf8_names_are_shown_lossily_and_marked() {
+            let path = RawPath::from_bytes(b"caf\xe9.rs");
+            assert!(path.is_lossy());
+            assert_eq!(path.to_string(), "caf\u{fffd}.rs (lossy)");
+            assert!(!RawPath::from("cafe.rs").is_lossy());
+
+            let author = ContributorKey::from_bytes(b"jose@example.com", b"Jos\xe9");
+            assert!(author.is_lossy());
+            assert_eq!(author.to_string(), "Jos\u{fffd}<jose@example.com> (lossy)");
+        }
     }
 }
@@ -1,28 +1,45 @@
 mod cli_setup;
 use crate::cli_setup::CliCommand;
-use crate::commands::{about_cmd, busfactor_cmd, contributors_cmd, hottest_cmd, metrics_cmd};
+use crate::commands::{
+    about_cmd, busfactor_cmd, cache_cmd, check_cmd, components_cmd, contributors_cmd, diff_cmd,
+    hottest_cmd, knowledge_cmd, metrics_cmd, report_cmd, trend_cmd,
+};
+use hotspot::error::HotspotError;
+use std::process;
 
 mod commands;
 
 fn main() {
     let app = cli_setup::capture_input();
     let matches = app.get_matches();
-    let command = cli_setup::parse(matches);
 
+    if let Err(e) = cli_setup::parse(matches).and_then(run) {
+        eprintln!("error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(command: CliCommand) -> Result<(), HotspotError> {
     match command {
         CliCommand::Nothing => {
             // Help will be printed by default.
-            ()
+            Ok(())
         }
         CliCommand::About => {
             about_cmd::execute();
+            Ok(())
         }
+        CliCommand::Components(config) => components_cmd::execute(config),
         CliCommand::Contributors(config) => contributors_cmd::execute(config),
         CliCommand::BusFactor(config) => busfactor_cmd::execute(config),
-        CliCommand::Metrics(config) => {
-            metrics_cmd::execute(config);
-        }
+        CliCommand::Check(config) => check_cmd::execute(config),
+        CliCommand::Diff(config) => diff_cmd::execute(config),
+        CliCommand::Cache(config) => cache_cmd::execute(config),
+        CliCommand::Metrics(config) => metrics_cmd::execute(config),
         CliCommand::Hottest(config) => hottest_cmd::execute(config),
+        CliCommand::Knowledge(config) => knowledge_cmd::execute(config),
+        CliCommand::Report(config) => report_cmd::execute(config),
+        CliCommand::Trend(config) => trend_cmd::execute(config),
         //CliCommand::Recommend => {}
-    };
+    }
 }
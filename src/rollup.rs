@@ -0,0 +1,317 @@
+//! Rolls per file results up into their directories or components, for reviewing a codebase by
+//! module rather than by file.
+
+use crate::components::Components;
+use crate::shared_types::{ContributorKey, RawPath, SpecificMetrics};
+use std::collections::{BTreeMap, HashSet};
+
+/// How files are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Directory {
+        /// How many directories down files are grouped, eg. `1` groups `src/a/b.rs` under `src`.
+        depth: usize,
+        /// Also rolls each group up into the directories above it, down from the top.
+        tree: bool,
+    },
+    /// By the components of the project configuration.
+    Component,
+}
+
+impl Grouping {
+    pub fn is_tree(&self) -> bool {
+        matches!(self, Grouping::Directory { tree: true, .. })
+    }
+}
+
+/// Totals of files grouped by directory, in path order so a directory comes right before what is
+/// in it, or by component in name order.
+#[derive(Debug, Clone)]
+pub struct Rollup<T> {
+    grouping: Grouping,
+    /// Number of leading path components outside the directory being reported on.
+    skip: usize,
+    components: Components,
+    groups: BTreeMap<Vec<String>, T>,
+}
+
+impl<T: Default> Rollup<T> {
+    /// Groups paths by their directories under `base`, all relative to the same root.
+    pub fn new(grouping: Grouping, base: Option<&RawPath>) -> Self {
+        Rollup {
+            grouping,
+            skip: base.map_or(0, |b| components(b).len()),
+            components: Components::default(),
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// Groups paths relative to the repository root by the components they are in. Every component
+    /// is listed, even when none of its files are added.
+    pub fn by_component(components: Components) -> Self {
+        let groups = components
+            .names()
+            .map(|name| (vec![name.to_string()], T::default()))
+            .collect();
+        Rollup {
+            grouping: Grouping::Component,
+            skip: 0,
+            components,
+            groups,
+        }
+    }
+
+    /// Calls `add` with the total of each group `path` is counted in.
+    pub fn add(&mut self, path: &RawPath, mut add: impl FnMut(&mut T)) {
+        let (depth, tree) = match self.grouping {
+            Grouping::Directory { depth, tree } => (depth, tree),
+            Grouping::Component => {
+                for name in self.components.of(path) {
+                    add(self.groups.entry(vec![name.to_string()]).or_default());
+                }
+                return;
+            }
+        };
+        let parts = components(path);
+        let dirs = parts.len().saturating_sub(1);
+        let group: Vec<String> = parts
+            .into_iter()
+            .take(dirs)
+            .skip(self.skip)
+            .take(depth)
+            .collect();
+        let first = if tree && !group.is_empty() {
+            1
+        } else {
+            group.len()
+        };
+        for len in first..=group.len() {
+            add(self.groups.entry(group[..len].to_vec()).or_default());
+        }
+    }
+}
+
+impl<T> Rollup<T> {
+    /// Each group's name and total. In a tree the name is indented by how deep the group is.
+    pub fn groups(&self) -> impl Iterator<Item = (String, &T)> + '_ {
+        let tree = self.grouping.is_tree();
+        self.groups.iter().map(move |(group, total)| {
+            let name = if group.is_empty() {
+                ".".to_string()
+            } else {
+                group.join("/")
+            };
+            let indent = if tree {
+                "  ".repeat(group.len().saturating_sub(1))
+            } else {
+                String::new()
+            };
+            (format!("{}{}", indent, name), total)
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+fn components(path: &RawPath) -> Vec<String> {
+    path.to_string_lossy()
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Code metrics of the analyzed files in a group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsTotal {
+    pub files: usize,
+    pub loc: i64,
+    pub cognitive: i64,
+    pub max_cognitive: i64,
+    pub cyclomatic: i64,
+    pub max_cyclomatic: i64,
+}
+
+impl MetricsTotal {
+    /// Counts `metrics` when its file was analyzed.
+    pub fn add(&mut self, metrics: &SpecificMetrics) {
+        if metrics.loc.is_none() {
+            return;
+        }
+        let cognitive = metrics.cognitive.unwrap_or_default();
+        let cyclomatic = metrics.cyclomatic.unwrap_or_default();
+        self.files += 1;
+        self.loc += metrics.loc.unwrap_or_default();
+        self.cognitive += cognitive;
+        self.max_cognitive = self.max_cognitive.max(cognitive);
+        self.cyclomatic += cyclomatic;
+        self.max_cyclomatic = self.max_cyclomatic.max(cyclomatic);
+    }
+
+    /// Average cognitive complexity of a file.
+    pub fn average_cognitive(&self) -> f64 {
+        if self.files == 0 {
+            0.0
+        } else {
+            self.cognitive as f64 / self.files as f64
+        }
+    }
+}
+
+/// How the files in a group were changed.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeTotal {
+    pub files: usize,
+    /// Commits that changed each file, summed, so a commit changing two files counts twice.
+    pub touches: u32,
+    pub contributors: HashSet<ContributorKey>,
+    /// Files only one person has changed.
+    pub single_contributor_files: usize,
+    pub last_changed_at: i64,
+}
+
+impl ChangeTotal {
+    /// Counts a file changed `touches` times by `contributors`, last at `last_changed_at`.
+    pub fn add<'a, I>(&mut self, touches: u32, contributors: I, last_changed_at: i64)
+    where
+        I: IntoIterator<Item = &'a ContributorKey>,
+    {
+        let mut count = 0;
+        for contributor in contributors {
+            count += 1;
+            self.contributors.insert(contributor.clone());
+        }
+        self.files += 1;
+        self.touches += touches;
+        if count == 1 {
+            self.single_contributor_files += 1;
+        }
+        self.last_changed_at = self.last_changed_at.max(last_changed_at);
+    }
+
+    /// How many people have changed files in the group.
+    pub fn bus_factor(&self) -> usize {
+        self.contributors.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzers::FileMetrics;
+    use crate::components::Components;
+    use crate::rollup::{ChangeTotal, Grouping, MetricsTotal, Rollup};
+    use crate::shared_types::{ContributorKey, RawPath, SpecificMetrics};
+
+    fn metrics(path: &str, loc: i64, cognitive: i64) -> SpecificMetrics {
+        let metrics = FileMetrics {
+            loc,
+            cognitive,
+            cyclomatic: 1,
+            functions: vec![],
+        };
+        SpecificMetrics::analyzed(RawPath::from(path), "test", metrics)
+    }
+
+    fn roll_up(grouping: Grouping, base: Option<&str>) -> Vec<(String, MetricsTotal)> {
+        let base = base.map(RawPath::from);
+        let mut rollup = Rollup::new(grouping, base.as_ref());
+        for m in [
+            metrics("src/lib.rs", 10, 2),
+            metrics("src/cmd/a.rs", 20, 6),
+            metrics("src/cmd/deep/b.rs", 30, 4),
+        ] {
+            rollup.add(&m.path, |total: &mut MetricsTotal| total.add(&m));
+        }
+        rollup
+            .groups()
+            .map(|(name, total)| (name, total.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn files_are_grouped_by_directory_down_to_a_depth() {
+        let flat = roll_up(
+            Grouping::Directory {
+                depth: 2,
+                tree: false,
+            },
+            None,
+        );
+        let names: Vec<&str> = flat.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["src", "src/cmd"]);
+        let cmd = &flat[1].1;
+        assert_eq!((cmd.files, cmd.loc, cmd.cognitive), (2, 50, 10));
+        assert_eq!(cmd.max_cognitive, 6);
+        assert_eq!(cmd.average_cognitive(), 5.0);
+    }
+
+    #[test]
+    fn trees_roll_groups_up_into_their_parents() {
+        let tree = roll_up(
+            Grouping::Directory {
+                depth: 2,
+                tree: true,
+            },
+            None,
+        );
+        let names: Vec<&str> = tree.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["src", "  src/cmd"]);
+        assert_eq!(tree[0].1.files, 3);
+    }
+
+    #[test]
+    fn groups_are_relative_to_the_base_directory() {
+        let flat = roll_up(
+            Grouping::Directory {
+                depth: 1,
+                tree: false,
+            },
+            Some("src"),
+        );
+        let names: Vec<&str> = flat.iter().map(|(n, _)| n.as_str()).collect();
+        // Files directly in `src` are in its `.` group.
+        assert_eq!(names, vec![".", "cmd"]);
+        assert_eq!(flat[1].1.files, 2);
+    }
+
+    #[test]
+    fn components_list_every_file_they_match() {
+        let mut components = Components::default();
+        components.add("cmd", &["src/cmd/**".to_string()]).unwrap();
+        components.add("all", &["src/**".to_string()]).unwrap();
+        components.add("docs", &["docs/**".to_string()]).unwrap();
+        let mut rollup = Rollup::by_component(components);
+        for m in [metrics("src/lib.rs", 10, 2), metrics("src/cmd/a.rs", 20, 6)] {
+            rollup.add(&m.path, |total: &mut MetricsTotal| total.add(&m));
+        }
+        let files: Vec<(String, usize)> = rollup.groups().map(|(n, t)| (n, t.files)).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("all".to_string(), 2),
+                ("cmd".to_string(), 1),
+                ("docs".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn change_totals_count_contributors_once() {
+        let alice = ContributorKey::new("alice@example.com".to_string(), "Alice".to_string());
+        let bob = ContributorKey::new("bob@example.com".to_string(), "Bob".to_string());
+        let mut total = ChangeTotal::default();
+        total.add(3, vec![&alice], 100);
+        total.add(2, vec![&alice, &bob], 50);
+        assert_eq!(total.files, 2);
+        assert_eq!(total.touches, 5);
+        assert_eq!(total.bus_factor(), 2);
+        assert_eq!(total.single_contributor_files, 1);
+        assert_eq!(total.last_changed_at, 100);
+    }
+}
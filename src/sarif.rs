@@ -0,0 +1,324 @@
+//! Writes findings as [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html),
+//! the format code scanning tools read to annotate pull requests.
+//!
+//! Paths are written relative to the `SRCROOT` base, which is set to the directory the paths are
+//! relative to when it is known. Otherwise neither the base nor references to it are written.
+
+use crate::check::Violation;
+use crate::config::Severity;
+use crate::hotspots::Hotspot;
+use crate::shared_types::RawPath;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SRCROOT: &str = "SRCROOT";
+pub const HOTSPOT_RULE: &str = "hotspot";
+pub const CHANGES_RULE: &str = "frequently-changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl From<Severity> for Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => Level::Error,
+            Severity::Warning => Level::Warning,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleConfiguration {
+    level: Level,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    short_description: Message,
+    default_configuration: RuleConfiguration,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    level: Level,
+    message: Message,
+    locations: Vec<Location>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<&'static str, i64>,
+}
+
+/// Collects results and the rules they refer to, then renders them as a SARIF log with one run.
+#[derive(Default)]
+pub struct SarifReport {
+    rules: Vec<ReportingDescriptor>,
+    results: Vec<SarifResult>,
+}
+
+impl SarifReport {
+    pub fn new() -> Self {
+        SarifReport::default()
+    }
+
+    fn rule(&mut self, id: &str, description: &str, level: Level) {
+        if !self.rules.iter().any(|r| r.id == id) {
+            self.rules.push(ReportingDescriptor {
+                id: id.to_string(),
+                short_description: Message {
+                    text: description.to_string(),
+                },
+                default_configuration: RuleConfiguration { level },
+            });
+        }
+    }
+
+    /// A rule violation found by [`crate::check::evaluate`], at the function's start line for the
+    /// function rules.
+    pub fn push_violation(&mut self, violation: &Violation) {
+        let level = Level::from(violation.severity);
+        self.rule(violation.rule.id(), violation.rule.description(), level);
+        self.results.push(SarifResult {
+            rule_id: violation.rule.id().to_string(),
+            level,
+            message: Message {
+                text: violation.message(),
+            },
+            locations: vec![location(&violation.path, violation.line)],
+            properties: vec![("value", violation.value), ("limit", violation.limit)]
+                .into_iter()
+                .collect(),
+        });
+    }
+
+    /// A file ranked by [`crate::hotspots::rank`], reported as a note.
+    pub fn push_hotspot(&mut self, hotspot: &Hotspot) {
+        self.rule(
+            HOTSPOT_RULE,
+            "The file is among the most complex and most often changed files",
+            Level::Note,
+        );
+        self.results.push(SarifResult {
+            rule_id: HOTSPOT_RULE.to_string(),
+            level: Level::Note,
+            message: Message {
+                text: format!(
+                    "hotspot score {}: cognitive complexity {} changed in {} commits",
+                    hotspot.score, hotspot.cognitive, hotspot.touches
+                ),
+            },
+            locations: vec![location(&hotspot.path, None)],
+            properties: vec![
+                ("score", hotspot.score),
+                ("cognitive", hotspot.cognitive),
+                ("touches", hotspot.touches as i64),
+                ("loc", hotspot.loc),
+            ]
+            .into_iter()
+            .collect(),
+        });
+    }
+
+    /// One of the files changed most often, from the `hottest` report, reported as a note.
+    pub fn push_changes(&mut self, path: &RawPath, touches: u32, contributors: usize) {
+        self.rule(
+            CHANGES_RULE,
+            "The file is among the most often changed files",
+            Level::Note,
+        );
+        self.results.push(SarifResult {
+            rule_id: CHANGES_RULE.to_string(),
+            level: Level::Note,
+            message: Message {
+                text: format!(
+                    "changed in {} commits by {} contributors",
+                    touches, contributors
+                ),
+            },
+            locations: vec![location(path, None)],
+            properties: vec![
+                ("touches", touches as i64),
+                ("contributors", contributors as i64),
+            ]
+            .into_iter()
+            .collect(),
+        });
+    }
+
+    /// The SARIF log as pretty printed JSON. `root` is the absolute directory the result paths are
+    /// relative to. Without it the paths are left relative with no base, as SARIF has no way to say
+    /// the base is unknown.
+    pub fn render(self, root: Option<&Path>) -> String {
+        let mut results = self.results;
+        let mut run = serde_json::json!({
+            "tool": {
+                "driver": {
+                    "name": "hotspot",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": self.rules,
+                }
+            },
+        });
+        match root {
+            Some(root) => {
+                let mut uri = format!("file://{}", encode(&RawPath::from_path(root)));
+                if !uri.ends_with('/') {
+                    uri.push('/');
+                }
+                let base = ArtifactLocation {
+                    uri,
+                    uri_base_id: None,
+                };
+                run["originalUriBaseIds"] = serde_json::json!({ SRCROOT: base });
+            }
+            None => {
+                for location in results.iter_mut().flat_map(|r| r.locations.iter_mut()) {
+                    location.physical_location.artifact_location.uri_base_id = None;
+                }
+            }
+        }
+        run["results"] = serde_json::json!(results);
+        let log = serde_json::json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [run]
+        });
+        serde_json::to_string_pretty(&log).unwrap_or_default()
+    }
+}
+
+fn location(path: &RawPath, start_line: Option<usize>) -> Location {
+    Location {
+        physical_location: PhysicalLocation {
+            artifact_location: ArtifactLocation {
+                uri: encode(path),
+                uri_base_id: Some(SRCROOT),
+            },
+            region: start_line.map(|start_line| Region { start_line }),
+        },
+    }
+}
+
+/// Percent-encodes the bytes of `path` that may not appear in a URI path.
+fn encode(path: &RawPath) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::Violation;
+    use crate::config::{RuleKind, Severity};
+    use crate::hotspots::Hotspot;
+    use crate::sarif::SarifReport;
+    use crate::shared_types::RawPath;
+    use std::path::Path;
+
+    #[test]
+    fn violations_and_hotspots_become_results_with_locations() {
+        let mut report = SarifReport::new();
+        report.push_violation(&Violation {
+            rule: RuleKind::FunctionCognitive,
+            severity: Severity::Warning,
+            path: RawPath::from("src/my file.rs"),
+            function: Some("parse".to_string()),
+            line: Some(12),
+            value: 30,
+            limit: 25,
+        });
+        report.push_hotspot(&Hotspot {
+            path: RawPath::from("src/main.rs"),
+            touches: 4,
+            cognitive: 10,
+            loc: 100,
+            score: 40,
+        });
+        let log: serde_json::Value =
+            serde_json::from_str(&report.render(Some(Path::new("/repo")))).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], "file:///repo/");
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["id"],
+            "function-cognitive"
+        );
+        let violation = &run["results"][0];
+        assert_eq!(violation["ruleId"], "function-cognitive");
+        assert_eq!(violation["level"], "warning");
+        let location = &violation["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/my%20file.rs");
+        assert_eq!(location["region"]["startLine"], 12);
+        let hotspot = &run["results"][1];
+        assert_eq!(hotspot["ruleId"], "hotspot");
+        assert_eq!(hotspot["level"], "note");
+        assert_eq!(hotspot["properties"]["score"], 40);
+    }
+
+    #[test]
+    fn frequently_changed_files_are_notes_without_a_region() {
+        let mut report = SarifReport::new();
+        report.push_changes(&RawPath::from("src/lib.rs"), 21, 3);
+        let log: serde_json::Value = serde_json::from_str(&report.render(None)).unwrap();
+        let run = &log["runs"][0];
+        assert!(run.get("originalUriBaseIds").is_none());
+        let result = &run["results"][0];
+        let artifact = &result["locations"][0]["physicalLocation"]["artifactLocation"];
+        assert_eq!(artifact["uri"], "src/lib.rs");
+        assert!(artifact.get("uriBaseId").is_none());
+        assert_eq!(result["ruleId"], "frequently-changed");
+        assert_eq!(result["level"], "note");
+        assert!(result["locations"][0]["physicalLocation"]["region"].is_null());
+        assert_eq!(result["properties"]["touches"], 21);
+        assert_eq!(result["properties"]["contributors"], 3);
+    }
+}
@@ -0,0 +1,353 @@
+//! Reads the files of a revision straight from the object database, so metrics can be taken without a
+//! working tree (eg. a bare mirror) or for a commit other than the one checked out. The files of a
+//! change are read from wherever the change is: a commit, the index or the working tree.
+
+use crate::analyzers::AnalyzerRegistry;
+use crate::cache::BlobCache;
+use crate::changes::{self, ChangeSet};
+use crate::error::HotspotError;
+use crate::shared_types::{RawPath, SpecificMetrics, FILE_GLOBS};
+use git2::{ObjectType, Oid, Repository, Tree};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use std::fs;
+use std::path::PathBuf;
+
+/// A file of a revision or change. Its contents are only read when it is measured.
+pub struct SnapshotFile {
+    /// Relative to the root of the repository.
+    pub path: RawPath,
+    pub contents: Contents,
+}
+
+/// Where the contents of a [`SnapshotFile`] are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Contents {
+    /// A blob in the object database, as for committed and staged files.
+    Blob(Oid),
+    /// A file on disk, as for changes in the working tree.
+    File(PathBuf),
+}
+
+/// The patterns of [`FILE_GLOBS`] split into the files to include and the names to skip.
+struct Matcher {
+    includes: GlobSet,
+    excluded_names: GlobSet,
+    excluded_dirs: GlobSet,
+}
+
+impl Matcher {
+    fn new() -> Result<Self, globset::Error> {
+        let mut includes = GlobSetBuilder::new();
+        let mut excluded_names = GlobSetBuilder::new();
+        let mut excluded_dirs = GlobSetBuilder::new();
+        for pattern in FILE_GLOBS.iter() {
+            match pattern.strip_prefix('!') {
+                Some(excluded) => match excluded.strip_suffix('/') {
+                    Some(dir) => excluded_dirs.add(Glob::new(dir)?),
+                    None => excluded_names.add(Glob::new(excluded)?),
+                },
+                None => includes.add(Glob::new(pattern)?),
+            };
+        }
+        Ok(Matcher {
+            includes: includes.build()?,
+            excluded_names: excluded_names.build()?,
+            excluded_dirs: excluded_dirs.build()?,
+        })
+    }
+
+    /// Whether the file at `path`, relative to the root of the repository, is measured.
+    fn is_match(&self, path: &RawPath) -> bool {
+        let names: Vec<String> = path
+            .as_bytes()
+            .split(|b| *b == b'/')
+            .map(|name| RawPath::from_bytes(name).to_string_lossy())
+            .collect();
+        match names.split_last() {
+            Some((file, dirs)) => {
+                dirs.iter().all(|dir| {
+                    !self.excluded_names.is_match(dir) && !self.excluded_dirs.is_match(dir)
+                }) && !self.excluded_names.is_match(file)
+                    && self.includes.is_match(file)
+            }
+            None => false,
+        }
+    }
+}
+
+fn matcher() -> Result<Matcher, HotspotError> {
+    Matcher::new().map_err(|e| HotspotError::InvalidConfig(format!("invalid file pattern: {}", e)))
+}
+
+/// The supported files of `revision`, optionally only those under the directory `scope`.
+pub fn files_at(
+    repo: &Repository,
+    revision: &str,
+    scope: Option<&RawPath>,
+) -> Result<Vec<SnapshotFile>, HotspotError> {
+    let tree = repo
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|source| HotspotError::BadRevision {
+            revision: revision.to_string(),
+            source,
+        })?;
+    let mut files = vec![];
+    collect(repo, &tree, &RawPath::from(""), &matcher()?, &mut files)?;
+    if let Some(scope) = scope {
+        files.retain(|f| f.path.is_within(scope));
+    }
+    Ok(files)
+}
+
+/// The supported files `changes` adds or modifies, optionally only those under the directory `scope`,
+/// with the contents the change gives them: as committed at `revision` for [`ChangeSet::Since`], as
+/// staged for [`ChangeSet::Staged`] and as on disk, including new files, for [`ChangeSet::WorkingTree`].
+pub fn changed_files(
+    repo: &Repository,
+    revision: &str,
+    changes: &ChangeSet,
+    scope: Option<&RawPath>,
+) -> Result<Vec<SnapshotFile>, HotspotError> {
+    let changed = changes::changed_files(repo, changes)?;
+    let mut files: Vec<SnapshotFile> = match changes {
+        ChangeSet::Since(_) => {
+            let mut files = files_at(repo, revision, scope)?;
+            files.retain(|f| changed.contains(&f.path));
+            return Ok(files);
+        }
+        ChangeSet::Staged => {
+            let index = repo.index()?;
+            changed
+                .into_iter()
+                .filter_map(|path| {
+                    let entry = index.get_path(&path.to_path_buf(), 0)?;
+                    Some(SnapshotFile {
+                        path,
+                        contents: Contents::Blob(entry.id),
+                    })
+                })
+                .collect()
+        }
+        ChangeSet::WorkingTree => {
+            let workdir = repo.workdir().ok_or_else(|| {
+                HotspotError::InvalidConfig("a bare repository has no working tree".to_string())
+            })?;
+            changed
+                .into_iter()
+                .map(|path| SnapshotFile {
+                    contents: Contents::File(workdir.join(path.to_path_buf())),
+                    path,
+                })
+                .collect()
+        }
+    };
+    let matcher = matcher()?;
+    files.retain(|f| matcher.is_match(&f.path) && scope.is_none_or(|s| f.path.is_within(s)));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Measures `files` of `repo` with `registry`, in parallel on `pool`. The contents of a file are only
+/// read when `cache` does not have its metrics, and are dropped once measured.
+pub fn measure(
+    pool: &ThreadPool,
+    repo: &Repository,
+    files: &[SnapshotFile],
+    registry: &AnalyzerRegistry,
+    cache: Option<&BlobCache>,
+) -> Result<Vec<SpecificMetrics>, HotspotError> {
+    // A repository cannot be shared between threads so each opens its own.
+    let git_dir = repo.path();
+    let metrics = pool.install(|| {
+        files
+            .par_iter()
+            .map_init(
+                || Repository::open(git_dir),
+                |repo, f| -> Result<SpecificMetrics, HotspotError> {
+                    let path = f.path.to_path_buf();
+                    match &f.contents {
+                        Contents::Blob(oid) => {
+                            let repo = repo
+                                .as_ref()
+                                .map_err(|e| git2::Error::new(e.code(), e.class(), e.message()))?;
+                            Ok(registry.analyze_blob(&path, *oid, cache, || {
+                                repo.find_blob(*oid).map(|blob| blob.content().to_vec())
+                            })?)
+                        }
+                        Contents::File(file) => {
+                            let contents = fs::read(file).map_err(|e| HotspotError::io(file, e))?;
+                            Ok(registry.analyze_cached(&path, &contents, cache))
+                        }
+                    }
+                },
+            )
+            .collect::<Result<_, HotspotError>>()
+    })?;
+    Ok(metrics)
+}
+
+fn collect(
+    repo: &Repository,
+    tree: &Tree,
+    dir: &RawPath,
+    matcher: &Matcher,
+    files: &mut Vec<SnapshotFile>,
+) -> Result<(), git2::Error> {
+    for entry in tree.iter() {
+        let name = RawPath::from_bytes(entry.name_bytes());
+        let name_text = name.to_string_lossy();
+        if matcher.excluded_names.is_match(&name_text) {
+            continue;
+        }
+        let path = dir.join(&name);
+        match entry.kind() {
+            Some(ObjectType::Tree) if !matcher.excluded_dirs.is_match(&name_text) => {
+                let subtree = repo.find_tree(entry.id())?;
+                collect(repo, &subtree, &path, matcher, files)?;
+            }
+            Some(ObjectType::Blob) if matcher.includes.is_match(&name_text) => {
+                files.push(SnapshotFile {
+                    path,
+                    contents: Contents::Blob(entry.id()),
+                });
+            }
+            // Submodules show up as commits and are not part of this repository's objects.
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity::Activity;
+    use crate::analyzers::AnalyzerRegistry;
+    use crate::changes::ChangeSet;
+    use crate::check::{self, CheckInput};
+    use crate::config::{Rule, RuleKind};
+    use crate::shared_types::RawPath;
+    use crate::snapshot::{changed_files, files_at, measure, Contents};
+    use crate::test_support::{commit, init_repo, temp_dir};
+    use git2::{Repository, Signature, Time};
+    use std::fs;
+    use std::path::Path;
+
+    const NESTED: &str = "fn f(x: i32) -> i32 {\n    if x > 1 {\n        if x > 2 {\n            return 2;\n        }\n    }\n    0\n}\n";
+
+    #[test]
+    fn supported_files_are_read_from_a_bare_repository() {
+        let dir = temp_dir("bare");
+        let repo = Repository::init_bare(&dir).unwrap();
+        let rs = repo.blob(b"fn a() {}\n").unwrap();
+        let txt = repo.blob(b"notes").unwrap();
+        let mut src = repo.treebuilder(None).unwrap();
+        src.insert("lib.rs", rs, 0o100644).unwrap();
+        src.insert("notes.txt", txt, 0o100644).unwrap();
+        let src = src.write().unwrap();
+        let mut hidden = repo.treebuilder(None).unwrap();
+        hidden.insert("skip.rs", rs, 0o100644).unwrap();
+        let hidden = hidden.write().unwrap();
+        let mut root = repo.treebuilder(None).unwrap();
+        root.insert("src", src, 0o040000).unwrap();
+        root.insert(".github", hidden, 0o040000).unwrap();
+        root.insert("main.rs", rs, 0o100644).unwrap();
+        let tree = repo.find_tree(root.write().unwrap()).unwrap();
+        let sig = Signature::new("Alice", "alice@example.com", &Time::new(1_000, 0)).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+
+        let paths: Vec<RawPath> = files_at(&repo, "HEAD", None)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![RawPath::from("main.rs"), RawPath::from("src/lib.rs")]
+        );
+
+        let scoped = files_at(&repo, "HEAD", Some(&RawPath::from("src"))).unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].contents, Contents::Blob(rs));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn uncommitted_changes_are_measured_as_staged_or_on_disk() {
+        let (dir, repo) = init_repo("uncommitted");
+        commit(&repo, "a.rs", "fn a() {}\n", "Alice", 1_000);
+        fs::write(dir.join("a.rs"), NESTED).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.join("b.rs"), NESTED).unwrap();
+        fs::write(dir.join("notes.txt"), "notes").unwrap();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let registry = AnalyzerRegistry::default();
+        let cognitive = |changes: ChangeSet| -> Vec<(String, Option<i64>)> {
+            let files = changed_files(&repo, "HEAD", &changes, None).unwrap();
+            measure(&pool, &repo, &files, &registry, None)
+                .unwrap()
+                .into_iter()
+                .map(|m| (m.path.to_string(), m.cognitive))
+                .collect()
+        };
+        assert_eq!(
+            cognitive(ChangeSet::Staged),
+            vec![("a.rs".to_string(), Some(3))]
+        );
+        assert_eq!(
+            cognitive(ChangeSet::WorkingTree),
+            vec![("a.rs".to_string(), Some(0)), ("b.rs".to_string(), Some(3))]
+        );
+
+        let files = changed_files(&repo, "HEAD", &ChangeSet::WorkingTree, None).unwrap();
+        let metrics = measure(&pool, &repo, &files, &registry, None).unwrap();
+        let violations = check::evaluate(
+            &[Rule::new(RuleKind::FunctionCognitive, 2)],
+            &CheckInput {
+                metrics: &metrics,
+                hotspots: &[],
+                activity: &Activity::default(),
+            },
+        )
+        .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, RawPath::from("b.rs"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn staged_files_under_a_directory_are_read_from_the_index_when_the_disk_differs() {
+        let (dir, repo) = init_repo("staged-scope");
+        commit(&repo, "src/a.rs", "fn a() {}\n", "Alice", 1_000);
+        commit(&repo, "b.rs", "fn b() {}\n", "Alice", 2_000);
+        fs::write(dir.join("src/a.rs"), NESTED).unwrap();
+        fs::write(dir.join("b.rs"), NESTED).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/a.rs")).unwrap();
+        index.add_path(Path::new("b.rs")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("src/a.rs"), "fn a() {}\n").unwrap();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let scope = RawPath::from("src");
+        let files = changed_files(&repo, "HEAD", &ChangeSet::Staged, Some(&scope)).unwrap();
+        let metrics = measure(&pool, &repo, &files, &AnalyzerRegistry::default(), None).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].path, RawPath::from("src/a.rs"));
+        assert_eq!(metrics[0].cognitive, Some(3));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
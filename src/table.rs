@@ -0,0 +1,450 @@
+//! The tables commands print their reports as, either drawn for a terminal or as GitHub flavoured
+//! Markdown for PR comments and wikis.
+//!
+//! In a terminal, tables are fitted to its width by cutting long paths and names, and values that
+//! need attention are coloured. When stdout is redirected they are printed in full without colour.
+
+use crate::shared_types::{truncate_left, truncate_right, OutputFormat};
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+use std::iter;
+use term_table::row::Row;
+use term_table::table_cell::{Alignment, TableCell};
+use term_table::TableStyle;
+
+/// Paths and names are not cut shorter than this, or their title, to fit a narrow terminal.
+const MIN_CUT_WIDTH: usize = 10;
+/// Used when a terminal does not say how wide it is.
+const DEFAULT_WIDTH: usize = 80;
+
+/// How tables are drawn for a terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Terminal {
+    /// Columns of text a table may take, long values are cut to fit. `None` prints them in full.
+    pub width: Option<usize>,
+    /// Colours values that need attention.
+    pub colour: bool,
+}
+
+impl Terminal {
+    /// Fits tables to stdout when it is a terminal, and colours them unless `no_colour` or the
+    /// `NO_COLOR` environment variable is set. Redirected output is plain and in full.
+    pub fn detect(no_colour: bool) -> Self {
+        if !io::stdout().is_terminal() {
+            return Terminal::default();
+        }
+        let no_colour_env = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Terminal {
+            width: Some(terminal_width()),
+            colour: !no_colour && !no_colour_env,
+        }
+    }
+}
+
+/// `COLUMNS` when set, otherwise the width of the terminal on stdout.
+fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|w| *w > 0)
+        .or_else(window_width)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+#[cfg(unix)]
+fn window_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a `winsize` through the pointer it is given.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 {
+        Some(size.ws_col as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn window_width() -> Option<usize> {
+    None
+}
+
+/// How much attention a value needs, shown by its colour in a terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Highlight {
+    #[default]
+    None,
+    /// Yellow.
+    Warning,
+    /// Red.
+    Danger,
+}
+
+impl Highlight {
+    fn paint(&self, value: String) -> String {
+        match self {
+            Highlight::None => value,
+            Highlight::Warning => format!("\x1b[33m{}\x1b[0m", value),
+            Highlight::Danger => format!("\x1b[31m{}\x1b[0m", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    value: String,
+    highlight: Highlight,
+}
+
+impl Cell {
+    pub fn new(value: impl ToString, highlight: Highlight) -> Self {
+        Cell {
+            value: value.to_string(),
+            highlight,
+        }
+    }
+
+    pub fn warning(value: impl ToString) -> Self {
+        Cell::new(value, Highlight::Warning)
+    }
+
+    pub fn danger(value: impl ToString) -> Self {
+        Cell::new(value, Highlight::Danger)
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::new(value, Highlight::None)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::new(value, Highlight::None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+}
+
+/// How a long value is shortened to fit a terminal. Markdown always has the full value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overflow {
+    Full,
+    /// Keeps the end, eg. the file name of a path.
+    CutLeft,
+    CutRight,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    title: String,
+    align: Align,
+    overflow: Overflow,
+}
+
+impl Column {
+    fn min_width(&self) -> usize {
+        self.title.chars().count().max(MIN_CUT_WIDTH)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    fn with(mut self, title: &str, align: Align, overflow: Overflow) -> Self {
+        self.columns.push(Column {
+            title: title.to_string(),
+            align,
+            overflow,
+        });
+        self
+    }
+
+    /// Adds a left aligned column.
+    pub fn column(self, title: &str) -> Self {
+        self.with(title, Align::Left, Overflow::Full)
+    }
+
+    /// Adds a right aligned column, for numbers.
+    pub fn right(self, title: &str) -> Self {
+        self.with(title, Align::Right, Overflow::Full)
+    }
+
+    /// Adds a column of paths, cut from the left when the table is too wide for the terminal.
+    pub fn path(self, title: &str) -> Self {
+        self.with(title, Align::Left, Overflow::CutLeft)
+    }
+
+    /// Adds a left aligned column cut from the right when the table is too wide for the terminal.
+    pub fn text(self, title: &str) -> Self {
+        self.with(title, Align::Left, Overflow::CutRight)
+    }
+
+    /// Adds a row with a value for each column, in the order they were added.
+    pub fn add_row<I>(&mut self, values: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Cell>,
+    {
+        self.rows.push(values.into_iter().map(Into::into).collect());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The table as Markdown for [`OutputFormat::Markdown`], otherwise drawn for a terminal.
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Markdown { collapse_after } => self.render_markdown(collapse_after),
+            OutputFormat::Table(terminal) => self.render_terminal(terminal),
+            _ => self.render_terminal(Terminal::default()),
+        }
+    }
+
+    fn render_terminal(&self, terminal: Terminal) -> String {
+        let widths = terminal.width.map(|width| self.fit(width));
+        let mut table = term_table::Table::new();
+        table.style = TableStyle::thin();
+        table.add_row(Row::new(
+            self.columns.iter().map(|c| TableCell::new(&c.title)),
+        ));
+        for row in &self.rows {
+            table.add_row(Row::new(self.columns.iter().zip(row).enumerate().map(
+                |(i, (column, cell))| {
+                    let value = match (column.overflow, widths.as_ref().map(|w| w[i])) {
+                        (Overflow::CutLeft, Some(width)) => {
+                            truncate_left(cell.value.clone(), width)
+                        }
+                        (Overflow::CutRight, Some(width)) => {
+                            truncate_right(cell.value.clone(), width)
+                        }
+                        _ => cell.value.clone(),
+                    };
+                    let value = if terminal.colour {
+                        cell.highlight.paint(value)
+                    } else {
+                        value
+                    };
+                    match column.align {
+                        Align::Left => TableCell::new(value),
+                        Align::Right => TableCell::builder(value)
+                            .alignment(Alignment::Right)
+                            .build(),
+                    }
+                },
+            )));
+        }
+        table.render()
+    }
+
+    /// How wide each column can be for the table to fit in `width`. The widest of the columns that
+    /// can be cut is narrowed first, until it fits or none can be cut any further.
+    fn fit(&self, width: usize) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.value.chars().count())
+                    .chain(iter::once(column.title.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        // A border and a space either side of each value, and the border closing each row.
+        let mut total = 1 + widths.iter().map(|w| w + 3).sum::<usize>();
+        while total > width {
+            let widest = self
+                .columns
+                .iter()
+                .enumerate()
+                .filter(|(i, c)| c.overflow != Overflow::Full && widths[*i] > c.min_width())
+                .max_by_key(|(i, _)| widths[*i])
+                .map(|(i, _)| i);
+            match widest {
+                Some(i) => {
+                    widths[i] -= 1;
+                    total -= 1;
+                }
+                None => break,
+            }
+        }
+        widths
+    }
+
+    /// Rows past the first `collapse_after` go in a second table inside a collapsed `<details>`.
+    fn render_markdown(&self, collapse_after: Option<usize>) -> String {
+        let shown = collapse_after
+            .unwrap_or(self.rows.len())
+            .min(self.rows.len());
+        let (rows, rest) = self.rows.split_at(shown);
+        let mut markdown = String::new();
+        if !rows.is_empty() || rest.is_empty() {
+            self.markdown_rows(&mut markdown, rows);
+        }
+        if !rest.is_empty() {
+            let _ = writeln!(
+                markdown,
+                "\n<details>\n<summary>{} more</summary>\n",
+                rest.len()
+            );
+            self.markdown_rows(&mut markdown, rest);
+            markdown.push_str("\n</details>\n");
+        }
+        markdown
+    }
+
+    fn markdown_rows(&self, markdown: &mut String, rows: &[Vec<Cell>]) {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        markdown.push_str(&line(
+            self.columns.iter().map(|c| escape(&c.title)).collect(),
+        ));
+        markdown.push_str(&line(
+            self.columns
+                .iter()
+                .map(|c| match c.align {
+                    Align::Left => "---".to_string(),
+                    Align::Right => "---:".to_string(),
+                })
+                .collect(),
+        ));
+        for row in rows {
+            markdown.push_str(&line(row.iter().map(|c| escape(&c.value)).collect()));
+        }
+    }
+}
+
+/// Prints a line of text that goes with the tables, as a paragraph of its own in Markdown.
+pub fn print_line(format: OutputFormat, text: &str) {
+    match format {
+        OutputFormat::Markdown { .. } => println!("{}\n", text),
+        _ => println!("{}", text),
+    }
+}
+
+/// `n` of `noun`, eg. "1 file" or "2 files", for the lines that sum up a report.
+pub fn count(n: usize, noun: &str) -> String {
+    match n {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    }
+}
+
+/// Escapes what would otherwise end a cell or be read as formatting or HTML. Without a `<` there
+/// is no tag, so `>` is left as is.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '|' | '*' | '`' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '<' => escaped.push_str("&lt;"),
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared_types::OutputFormat;
+    use crate::table::{count, Cell, Table, Terminal};
+
+    fn table() -> Table {
+        let mut table = Table::new().path("Path").right("Changes");
+        table.add_row(vec![Cell::from("src/commands/a|b.rs"), Cell::danger(12)]);
+        table.add_row(vec!["Dev <dev@example.com>".to_string(), 3.to_string()]);
+        table.add_row(vec!["c.rs".to_string(), 1.to_string()]);
+        table
+    }
+
+    #[test]
+    fn markdown_has_full_escaped_values_and_alignments() {
+        let markdown = table().render(OutputFormat::Markdown {
+            collapse_after: None,
+        });
+        assert_eq!(
+            markdown,
+            "| Path | Changes |\n\
+             | --- | ---: |\n\
+             | src/commands/a\\|b.rs | 12 |\n\
+             | Dev &lt;dev@example.com> | 3 |\n\
+             | c.rs | 1 |\n"
+        );
+    }
+
+    #[test]
+    fn long_markdown_tables_are_collapsed() {
+        let markdown = table().render(OutputFormat::Markdown {
+            collapse_after: Some(2),
+        });
+        assert!(markdown.contains("| c.rs | 1 |\n\n</details>\n"));
+        assert!(markdown.contains("<details>\n<summary>1 more</summary>\n\n| Path | Changes |"));
+        assert_eq!(markdown.matches("| Path |").count(), 2);
+    }
+
+    #[test]
+    fn terminal_tables_are_cut_to_fit() {
+        let terminal = Terminal {
+            width: Some(27),
+            colour: false,
+        };
+        let rendered = table().render(OutputFormat::Table(terminal));
+        assert!(rendered.lines().all(|l| l.chars().count() <= 27));
+        assert!(rendered.contains("│ ...nds/a|b.rs │"));
+        assert!(!rendered.contains('\x1b'));
+    }
+
+    #[test]
+    fn plain_tables_are_in_full_and_coloured_on_request() {
+        let rendered = table().render(OutputFormat::Table(Terminal::default()));
+        assert!(rendered.contains("│ src/commands/a|b.rs   │"));
+        assert!(!rendered.contains('\x1b'));
+
+        let terminal = Terminal {
+            width: None,
+            colour: true,
+        };
+        let rendered = table().render(OutputFormat::Table(terminal));
+        assert!(rendered.contains("\x1b[31m12\x1b[0m"));
+    }
+
+    #[test]
+    fn counts_are_singular_for_one() {
+        assert_eq!(count(0, "file"), "0 files");
+        assert_eq!(count(1, "file"), "1 file");
+        assert_eq!(
+            count(2, "new or worse violation"),
+            "2 new or worse violations"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Teams of contributors, set in the `[teams]` table of the project configuration, and the history
+//! of the repository seen by team rather than by person.
+
+use crate::config::ProjectConfig;
+use crate::error::HotspotError;
+use crate::history::CommitRecord;
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::Repository;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// What the contributors no team lists are reported as. It is not a team, so it is left out when
+/// counting the teams that changed a file.
+pub const NO_TEAM: &str = "(no team)";
+
+/// Matches contributors to teams by email or name. Someone listed by more than one team is in the
+/// first of them by name.
+#[derive(Debug, Clone, Default)]
+pub struct Teams {
+    teams: Vec<(String, GlobSet)>,
+}
+
+impl Teams {
+    /// The teams of the configuration at `path`, or of the one in the root of `repo`, failing when
+    /// it names none.
+    pub fn load(path: Option<&Path>, repo: &Repository) -> Result<Self, HotspotError> {
+        let teams = ProjectConfig::load(path, repo)?.teams()?;
+        if teams.is_empty() {
+            return Err(HotspotError::InvalidConfig(
+                "no teams are set in the [teams] table of the configuration".to_string(),
+            ));
+        }
+        Ok(teams)
+    }
+
+    /// Adds a team of the people whose email or name matches one of `members`, ignoring case.
+    /// Members can be globs, eg. `*@payments.example.com`.
+    pub fn add(&mut self, name: &str, members: &[String]) -> Result<(), HotspotError> {
+        let mut builder = GlobSetBuilder::new();
+        for member in members {
+            let glob = GlobBuilder::new(member)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| {
+                    HotspotError::InvalidConfig(format!(
+                        "team {}: invalid member '{}': {}",
+                        name, member, e
+                    ))
+                })?;
+            builder.add(glob);
+        }
+        let members = builder
+            .build()
+            .map_err(|e| HotspotError::InvalidConfig(format!("team {}: {}", name, e)))?;
+        self.teams.push((name.to_string(), members));
+        self.teams.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(())
+    }
+
+    /// The team `contributor` is in, `None` when no team lists them.
+    pub fn team_of(&self, contributor: &ContributorKey) -> Option<&str> {
+        self.teams
+            .iter()
+            .find(|(_, members)| {
+                members.is_match(contributor.email()) || members.is_match(contributor.name())
+            })
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TeamActivity {
+    /// The people in the team who have committed.
+    pub members: HashSet<ContributorKey>,
+    pub commits: u32,
+    pub files: HashSet<RawPath>,
+}
+
+/// Which teams changed a file.
+#[derive(Debug, Clone, Default)]
+pub struct FileTeams {
+    /// Number of commits that changed the file.
+    pub touches: u32,
+    pub teams: HashSet<String>,
+    /// The people who changed the file that no team lists.
+    pub without_team: HashSet<ContributorKey>,
+}
+
+/// The commits of a history by team and the teams that changed each file.
+#[derive(Debug, Clone, Default)]
+pub struct TeamHistory {
+    pub teams: BTreeMap<String, TeamActivity>,
+    pub files: HashMap<RawPath, FileTeams>,
+}
+
+impl TeamHistory {
+    /// Adds a commit, in any order. Commits by people no team lists are kept under [`NO_TEAM`].
+    pub fn add(&mut self, teams: &Teams, record: CommitRecord) {
+        let team = teams.team_of(&record.author).map(String::from);
+        let activity = self
+            .teams
+            .entry(team.clone().unwrap_or_else(|| NO_TEAM.to_string()))
+            .or_default();
+        activity.commits += 1;
+        activity.members.insert(record.author.clone());
+        for delta in record.deltas {
+            let file = self.files.entry(delta.path.clone()).or_default();
+            file.touches += 1;
+            match &team {
+                Some(team) => {
+                    file.teams.insert(team.clone());
+                }
+                None => {
+                    file.without_team.insert(record.author.clone());
+                }
+            }
+            activity.files.insert(delta.path);
+        }
+    }
+
+    /// The number of teams that have committed, leaving out [`NO_TEAM`].
+    pub fn team_count(&self) -> usize {
+        self.teams.keys().filter(|t| *t != NO_TEAM).count()
+    }
+
+    /// The files changed by more than one team, those changed by the most teams and then most
+    /// often first. These are where teams have to coordinate.
+    pub fn coordination_hotspots(&self) -> Vec<(&RawPath, &FileTeams)> {
+        let mut files: Vec<(&RawPath, &FileTeams)> = self
+            .files
+            .iter()
+            .filter(|(_, f)| f.teams.len() > 1)
+            .collect();
+        files.sort_by(|a, b| {
+            b.1.teams
+                .len()
+                .cmp(&a.1.teams.len())
+                .then_with(|| b.1.touches.cmp(&a.1.touches))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{CommitRecord, FileDelta};
+    use crate::shared_types::{ContributorKey, RawPath};
+    use crate::teams::{TeamHistory, Teams, NO_TEAM};
+    use git2::{Delta, Oid};
+
+    fn teams() -> Teams {
+        let mut teams = Teams::default();
+        teams
+            .add("payments", &["*@payments.example.com".to_string()])
+            .unwrap();
+        teams
+            .add("web", &["Carol".to_string(), "dan@example.com".to_string()])
+            .unwrap();
+        teams
+    }
+
+    fn person(email: &str, name: &str) -> ContributorKey {
+        ContributorKey::new(email.to_string(), name.to_string())
+    }
+
+    fn commit(author: ContributorKey, paths: &[&str]) -> CommitRecord {
+        CommitRecord {
+            oid: Oid::zero(),
+            author,
+            time: 0,
+            deltas: paths
+                .iter()
+                .map(|p| FileDelta {
+                    path: RawPath::from(*p),
+                    status: Delta::Modified,
+                    additions: 1,
+                    deletions: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn contributors_are_matched_by_email_or_name() {
+        let teams = teams();
+        assert_eq!(
+            teams.team_of(&person("Alice@Payments.example.com", "Alice")),
+            Some("payments")
+        );
+        assert_eq!(
+            teams.team_of(&person("carol@home.example", "carol")),
+            Some("web")
+        );
+        assert_eq!(teams.team_of(&person("eve@example.com", "Eve")), None);
+    }
+
+    #[test]
+    fn files_changed_by_several_teams_need_coordination() {
+        let teams = teams();
+        let mut history = TeamHistory::default();
+        history.add(
+            &teams,
+            commit(
+                person("alice@payments.example.com", "Alice"),
+                &["api.rs", "pay.rs"],
+            ),
+        );
+        history.add(
+            &teams,
+            commit(person("dan@example.com", "Dan"), &["api.rs"]),
+        );
+        history.add(
+            &teams,
+            commit(person("eve@example.com", "Eve"), &["api.rs", "pay.rs"]),
+        );
+        assert_eq!(history.teams["payments"].commits, 1);
+        assert_eq!(history.teams["payments"].files.len(), 2);
+        assert_eq!(history.teams[NO_TEAM].members.len(), 1);
+        assert_eq!(history.team_count(), 2);
+        // Someone without a team changing a file does not make another team to coordinate with.
+        let hotspots: Vec<(String, usize)> = history
+            .coordination_hotspots()
+            .into_iter()
+            .map(|(p, f)| (p.to_string(), f.teams.len()))
+            .collect();
+        assert_eq!(hotspots, vec![("api.rs".to_string(), 2)]);
+        assert_eq!(history.files[&RawPath::from("pay.rs")].teams.len(), 1);
+        assert_eq!(
+            history.files[&RawPath::from("pay.rs")].without_team.len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Git repositories and commits for the tests to work on.
+
+use crate::history::{CommitRecord, FileDelta};
+use crate::shared_types::{ContributorKey, RawPath};
+use git2::{Delta, Oid, Repository, Signature, Time};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An empty directory named after `name` under the temporary directory, unique to this process.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hotspot-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A new repository with no commits in [`temp_dir`].
+pub fn init_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = temp_dir(name);
+    let repo = Repository::init(&dir).unwrap();
+    (dir, repo)
+}
+
+/// Writes `contents` to `file` and commits it on top of HEAD as `name` at `time`.
+pub fn commit(repo: &Repository, file: &str, contents: &str, name: &str, time: i64) {
+    let path = repo.workdir().unwrap().join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let email = format!("{}@example.com", name.to_lowercase());
+    let sig = Signature::new(name, &email, &Time::new(time, 0)).unwrap();
+    let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, "change", &tree, &parents)
+        .unwrap();
+}
+
+/// A commit by `author`, at `<author>@example.com`, at `time` modifying each of `paths` with two
+/// lines added and one deleted.
+pub fn record(author: &str, time: i64, paths: &[&str]) -> CommitRecord {
+    CommitRecord {
+        oid: Oid::zero(),
+        author: ContributorKey::new(format!("{}@example.com", author), author.to_string()),
+        time,
+        deltas: paths
+            .iter()
+            .map(|p| FileDelta {
+                path: RawPath::from(*p),
+                status: Delta::Modified,
+                additions: 2,
+                deletions: 1,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,357 @@
+//! Draws files as a nested treemap in SVG: each directory is a box holding its files and
+//! subdirectories, each file's area is its lines of code and its colour is how often it changed.
+//!
+//! The SVG has no external references so it can be embedded in wikis or opened offline. Rectangles are
+//! laid out with the squarified algorithm (Bruls, Huizing and van Wijk) to keep them close to square.
+
+use crate::html::escape;
+use crate::shared_types::RawPath;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Height of the strip a directory's name is written in.
+const HEADER: f64 = 14.0;
+const PADDING: f64 = 2.0;
+const FONT_SIZE: f64 = 10.0;
+
+/// A file to draw.
+#[derive(Debug, Clone)]
+pub struct TreemapItem {
+    pub path: RawPath,
+    pub loc: i64,
+    pub touches: u32,
+    pub cognitive: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rect {
+    fn inset(&self, top: f64, side: f64) -> Rect {
+        Rect {
+            x: self.x + side,
+            y: self.y + top,
+            w: (self.w - 2.0 * side).max(0.0),
+            h: (self.h - top - side).max(0.0),
+        }
+    }
+}
+
+/// Splits `rect` into one rectangle per value, each with an area proportional to its value. `values`
+/// should be sorted largest first for the best aspect ratios. The rectangles are in the order of
+/// `values`.
+pub fn squarify(values: &[f64], rect: Rect) -> Vec<Rect> {
+    let total: f64 = values.iter().sum();
+    if total <= 0.0 {
+        return values
+            .iter()
+            .map(|_| Rect {
+                w: 0.0,
+                h: 0.0,
+                ..rect
+            })
+            .collect();
+    }
+    let scale = rect.w * rect.h / total;
+    let areas: Vec<f64> = values.iter().map(|v| v * scale).collect();
+    let mut laid_out = Vec::with_capacity(areas.len());
+    let mut free = rect;
+    let mut row: Vec<f64> = vec![];
+    for &area in &areas {
+        let side = free.w.min(free.h);
+        let mut extended = row.clone();
+        extended.push(area);
+        if row.is_empty() || worst(&extended, side) <= worst(&row, side) {
+            row = extended;
+        } else {
+            lay_out_row(&row, &mut free, &mut laid_out);
+            row = vec![area];
+        }
+    }
+    lay_out_row(&row, &mut free, &mut laid_out);
+    laid_out
+}
+
+/// The worst aspect ratio of the rectangles in `row` placed along a side of length `side`.
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side = side * side;
+    let sum = sum * sum;
+    (side * max / sum).max(sum / (side * min))
+}
+
+/// Places `row` along the shorter side of `free` and shrinks `free` to what is left.
+fn lay_out_row(row: &[f64], free: &mut Rect, laid_out: &mut Vec<Rect>) {
+    let sum: f64 = row.iter().sum();
+    if sum <= 0.0 {
+        return;
+    }
+    if free.w >= free.h {
+        let width = sum / free.h;
+        let mut y = free.y;
+        for area in row {
+            let h = area / width;
+            laid_out.push(Rect {
+                x: free.x,
+                y,
+                w: width,
+                h,
+            });
+            y += h;
+        }
+        free.x += width;
+        free.w -= width;
+    } else {
+        let height = sum / free.w;
+        let mut x = free.x;
+        for area in row {
+            let w = area / height;
+            laid_out.push(Rect {
+                x,
+                y: free.y,
+                w,
+                h: height,
+            });
+            x += w;
+        }
+        free.y += height;
+        free.h -= height;
+    }
+}
+
+/// A directory, or a file when it has no children.
+#[derive(Default)]
+struct Node<'a> {
+    children: BTreeMap<String, Node<'a>>,
+    item: Option<&'a TreemapItem>,
+    loc: i64,
+}
+
+impl<'a> Node<'a> {
+    fn insert(&mut self, parts: &[&str], item: &'a TreemapItem) {
+        self.loc += item.loc;
+        match parts {
+            [] => self.item = Some(item),
+            [first, rest @ ..] => self
+                .children
+                .entry(first.to_string())
+                .or_default()
+                .insert(rest, item),
+        }
+    }
+
+    /// Merges directories that only hold one directory, eg. `src` holding only `main`, into one
+    /// `src/main` box.
+    fn collapse(self, name: String) -> (String, Node<'a>) {
+        let mut node = self;
+        let mut name = name;
+        while node.item.is_none() && node.children.len() == 1 {
+            let only_dir = node
+                .children
+                .values()
+                .next()
+                .is_some_and(|c| c.item.is_none());
+            if !only_dir {
+                break;
+            }
+            let (child_name, child) = node.children.into_iter().next().unwrap();
+            name = if name.is_empty() {
+                child_name
+            } else {
+                format!("{}/{}", name, child_name)
+            };
+            node = child;
+        }
+        let children = node
+            .children
+            .into_iter()
+            .map(|(n, c)| c.collapse(n))
+            .collect();
+        (
+            name,
+            Node {
+                children,
+                item: node.item,
+                loc: node.loc,
+            },
+        )
+    }
+}
+
+/// The colour of a file that changed `touches` times when the most changed file changed `max` times,
+/// from pale yellow for rarely changed files to red for the most changed ones.
+fn colour(touches: u32, max: u32) -> String {
+    let heat = if max == 0 {
+        0.0
+    } else {
+        // The square root spreads out the many files that only changed a few times.
+        (touches as f64 / max as f64).sqrt()
+    };
+    let hue = 60.0 * (1.0 - heat);
+    let lightness = 85.0 - 35.0 * heat;
+    format!("hsl({:.0},90%,{:.0}%)", hue, lightness)
+}
+
+/// The treemap of `items` as an SVG document `width` by `height` pixels. Files without lines of code
+/// have no area and are left out.
+pub fn render_svg(items: &[TreemapItem], width: f64, height: f64) -> String {
+    let mut root = Node::default();
+    for item in items.iter().filter(|i| i.loc > 0) {
+        let path = item.path.to_string_lossy();
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        root.insert(&parts, item);
+    }
+    let (name, root) = root.collapse(String::new());
+    let max_touches = items.iter().map(|i| i.touches).max().unwrap_or(0);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif" font-size="{2}">"#,
+        width, height, FONT_SIZE
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        width, height
+    );
+    let bounds = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: width,
+        h: height,
+    };
+    draw(&mut svg, "", &name, &root, bounds, max_touches);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draws `node`, called `name` inside the directory `parent`, and everything in it.
+fn draw(svg: &mut String, parent: &str, name: &str, node: &Node, rect: Rect, max_touches: u32) {
+    if rect.w < 1.0 || rect.h < 1.0 {
+        return;
+    }
+    if let Some(item) = node.item {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="white" stroke-width="0.5"><title>{}
+{} lines, {} changes, cognitive {}</title></rect>"#,
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            colour(item.touches, max_touches),
+            escape(&item.path.to_string()),
+            item.loc,
+            item.touches,
+            item.cognitive
+        );
+        label(svg, name, rect, rect.y + FONT_SIZE + 1.0);
+        return;
+    }
+
+    let path = match (parent, name) {
+        ("", _) => name.to_string(),
+        (_, "") => parent.to_string(),
+        _ => format!("{}/{}", parent, name),
+    };
+    let inner = if name.is_empty() {
+        rect
+    } else {
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#eee" stroke="#999" stroke-width="0.5"><title>{}</title></rect>"##,
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            escape(&path)
+        );
+        label(svg, name, rect, rect.y + FONT_SIZE + 1.0);
+        rect.inset(HEADER, PADDING)
+    };
+    let mut children: Vec<(&String, &Node)> = node.children.iter().collect();
+    children.sort_by_key(|(_, c)| std::cmp::Reverse(c.loc));
+    let values: Vec<f64> = children.iter().map(|(_, c)| c.loc as f64).collect();
+    for ((child_name, child), child_rect) in children.iter().zip(squarify(&values, inner)) {
+        draw(svg, &path, child_name, child, child_rect, max_touches);
+    }
+}
+
+/// Writes `text` at the top left of `rect` when it fits.
+fn label(svg: &mut String, text: &str, rect: Rect, baseline: f64) {
+    // Roughly the width of an average character at the font size.
+    let text_width = text.chars().count() as f64 * FONT_SIZE * 0.6;
+    if text_width + 4.0 <= rect.w && FONT_SIZE + 2.0 <= rect.h {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" pointer-events="none">{}</text>"#,
+            rect.x + 2.0,
+            baseline,
+            escape(text)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared_types::RawPath;
+    use crate::treemap::{render_svg, squarify, Rect, TreemapItem};
+
+    #[test]
+    fn squarified_rectangles_fill_the_area_in_proportion() {
+        let bounds = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 60.0,
+            h: 40.0,
+        };
+        let values = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let rects = squarify(&values, bounds);
+        assert_eq!(rects.len(), values.len());
+        let total: f64 = values.iter().sum();
+        for (value, rect) in values.iter().zip(&rects) {
+            let expected = value / total * 60.0 * 40.0;
+            assert!((rect.w * rect.h - expected).abs() < 1e-6);
+            assert!(rect.x >= -1e-9 && rect.x + rect.w <= 60.0 + 1e-9);
+            assert!(rect.y >= -1e-9 && rect.y + rect.h <= 40.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn files_are_drawn_inside_their_directories() {
+        let item = |path: &str, loc: i64, touches: u32| TreemapItem {
+            path: RawPath::from(path),
+            loc,
+            touches,
+            cognitive: 1,
+        };
+        let svg = render_svg(
+            &[
+                item("src/core/a.rs", 300, 10),
+                item("src/core/b.rs", 100, 1),
+                item("src/ui/<c>.rs", 200, 0),
+                item("empty.rs", 0, 5),
+            ],
+            400.0,
+            300.0,
+        );
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        // Everything with lines of code is in `src` so it is the outermost box.
+        assert!(svg.contains("<title>src</title>"));
+        assert!(svg.contains("<title>src/core</title>"));
+        assert!(svg.contains("<title>src/core/a.rs\n300 lines, 10 changes, cognitive 1</title>"));
+        assert!(svg.contains("src/ui/&lt;c&gt;.rs"));
+        assert!(!svg.contains("empty.rs"));
+        // The most changed file is red, the unchanged one pale yellow.
+        assert!(svg.contains(r#"fill="hsl(0,90%,50%)""#));
+        assert!(svg.contains(r#"fill="hsl(60,90%,85%)""#));
+    }
+}
@@ -0,0 +1,218 @@
+//! Picks the commits to measure for a complexity trend and adds up what was measured at each of them.
+//!
+//! Samples follow the first parent from `HEAD`, so the commits of a merged branch are not sampled on
+//! their own but through the merge that brought them in.
+
+use crate::error::HotspotError;
+use crate::shared_types::SpecificMetrics;
+use git2::{Oid, Repository, Sort};
+
+const SECONDS_IN_WEEK: i64 = 7 * 24 * 60 * 60;
+/// The unix epoch is a Thursday, so weeks counted from it are moved back to start on the Monday before.
+const EPOCH_TO_MONDAY: i64 = 3 * 24 * 60 * 60;
+
+/// How often the history is sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    /// Every N commits counting back from `HEAD`.
+    Commits(usize),
+    /// The last commit of each week that has any. Weeks run from Monday to Sunday in UTC.
+    Weekly,
+    /// Every tag that points at a commit.
+    Tags,
+}
+
+/// A commit the repository is measured at.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub oid: Oid,
+    /// Seconds since the unix epoch.
+    pub time: i64,
+    /// The tag name, or the abbreviated commit id when not sampling tags.
+    pub label: String,
+}
+
+/// The metrics of every supported file at a sample.
+pub struct TrendPoint {
+    pub sample: Sample,
+    pub files: Vec<SpecificMetrics>,
+}
+
+impl TrendPoint {
+    pub fn loc(&self) -> i64 {
+        self.files.iter().filter_map(|f| f.loc).sum()
+    }
+
+    pub fn cognitive(&self) -> i64 {
+        self.files.iter().filter_map(|f| f.cognitive).sum()
+    }
+
+    pub fn cyclomatic(&self) -> i64 {
+        self.files.iter().filter_map(|f| f.cyclomatic).sum()
+    }
+
+    /// The number of files that could be analyzed.
+    pub fn analyzed_files(&self) -> usize {
+        self.files.iter().filter(|f| f.loc.is_some()).count()
+    }
+}
+
+/// The commits to measure, oldest first. The newest commit is always included when sampling by commits.
+pub fn samples(repo: &Repository, interval: Interval) -> Result<Vec<Sample>, HotspotError> {
+    if let Interval::Tags = interval {
+        return tag_samples(repo);
+    }
+    let mut commits = first_parent_history(repo)?;
+    commits.reverse();
+    let picked: Vec<(Oid, i64)> = match interval {
+        Interval::Commits(0) => {
+            return Err(HotspotError::InvalidConfig(
+                "cannot sample every 0 commits".to_string(),
+            ))
+        }
+        Interval::Commits(every) => {
+            let newest = commits.len().saturating_sub(1);
+            commits
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| (newest - i) % every == 0)
+                .map(|(_, commit)| commit)
+                .collect()
+        }
+        _ => {
+            let mut picked: Vec<(Oid, i64)> = vec![];
+            for (oid, time) in commits {
+                match picked.last_mut() {
+                    Some(last) if week_of(last.1) == week_of(time) => *last = (oid, time),
+                    _ => picked.push((oid, time)),
+                }
+            }
+            picked
+        }
+    };
+    Ok(picked
+        .into_iter()
+        .map(|(oid, time)| Sample {
+            oid,
+            time,
+            label: short_id(oid),
+        })
+        .collect())
+}
+
+/// The ISO week `time` falls in, counted from the week of the unix epoch.
+fn week_of(time: i64) -> i64 {
+    (time + EPOCH_TO_MONDAY).div_euclid(SECONDS_IN_WEEK)
+}
+
+/// The commits on the first parent chain from `HEAD` and their times, newest first.
+fn first_parent_history(repo: &Repository) -> Result<Vec<(Oid, i64)>, HotspotError> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.simplify_first_parent()?;
+    revwalk
+        .push_head()
+        .map_err(|source| HotspotError::BadRevision {
+            revision: "HEAD".to_string(),
+            source,
+        })?;
+    let mut commits = vec![];
+    for oid in revwalk {
+        let oid = oid?;
+        commits.push((oid, repo.find_commit(oid)?.time().seconds()));
+    }
+    Ok(commits)
+}
+
+fn tag_samples(repo: &Repository) -> Result<Vec<Sample>, HotspotError> {
+    let mut samples = vec![];
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let commit = match repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit())
+        {
+            Ok(commit) => commit,
+            // Tags of trees or blobs have nothing to measure.
+            Err(_) => continue,
+        };
+        samples.push(Sample {
+            oid: commit.id(),
+            time: commit.time().seconds(),
+            label: name.to_string(),
+        });
+    }
+    samples.sort_by_key(|s| s.time);
+    Ok(samples)
+}
+
+fn short_id(oid: Oid) -> String {
+    oid.to_string().chars().take(7).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{commit, init_repo};
+    use crate::trend::{samples, Interval};
+    use git2::Repository;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    /// Five commits three days apart from the unix epoch, which spans three weeks.
+    fn test_repo(name: &str) -> (PathBuf, Repository) {
+        let (dir, repo) = init_repo(name);
+        for day in 0..5 {
+            commit(
+                &repo,
+                "a.rs",
+                &"fn a() {}\n".repeat(day + 1),
+                "Alice",
+                3 * day as i64 * DAY,
+            );
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn sampling_every_n_commits_counts_back_from_head() {
+        let (dir, repo) = test_repo("trend-commits");
+        let times: Vec<i64> = samples(&repo, Interval::Commits(2))
+            .unwrap()
+            .iter()
+            .map(|s| s.time)
+            .collect();
+        assert_eq!(times, vec![0, 6 * DAY, 12 * DAY]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn weekly_sampling_keeps_the_last_commit_of_each_week() {
+        let (dir, repo) = test_repo("trend-weekly");
+        let times: Vec<i64> = samples(&repo, Interval::Weekly)
+            .unwrap()
+            .iter()
+            .map(|s| s.time)
+            .collect();
+        // The epoch is a Thursday, so days 0 to 3 end the first week and days 4 to 10 are the second.
+        assert_eq!(times, vec![3 * DAY, 9 * DAY, 12 * DAY]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tag_sampling_is_ordered_by_commit_time() {
+        let (dir, repo) = test_repo("trend-tags");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let first = head.parent(0).unwrap().parent(0).unwrap();
+        repo.tag_lightweight("v2", head.as_object(), false).unwrap();
+        repo.tag_lightweight("v1", first.as_object(), false)
+            .unwrap();
+        let labels: Vec<String> = samples(&repo, Interval::Tags)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.label)
+            .collect();
+        assert_eq!(labels, vec!["v1", "v2"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}